@@ -1,8 +1,14 @@
 use clap::Parser;
 use std::io;
 
-use super::{options::Opts, CommandSystemLogPlugin};
-use crate::run::CommandSystemSimpleReport;
+use super::report::write_reports;
+use super::tail::OutputTails;
+use super::{
+    eprint_message, options::Opts, CommandSystemJsonlPlugin, CommandSystemLogPlugin, LogOptions,
+    MessageLevel,
+};
+use crate::run::{CommandSystemPlugin, CommandSystemSimpleReport, LabeledCommandData};
+use crate::{OutputMode, RunConfig};
 
 pub async fn run() -> io::Result<CommandSystemSimpleReport> {
     let args = std::env::args_os();
@@ -16,28 +22,54 @@ pub async fn run() -> io::Result<CommandSystemSimpleReport> {
 
     let opts: Opts = Opts::parse_from(args);
 
-    let config = opts.try_into_config().or_else(|err| {
-        Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            format!("{}", err),
-        ))
-    })?;
+    let config = opts
+        .try_into_config()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}", err)))?;
 
-    let mut system =
-        crate::run::spawn_from_run_config_with_plugin(config, CommandSystemLogPlugin::new());
+    let log_options = LogOptions::from_config(&config)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}", err)))?;
+
+    if config.output == OutputMode::Jsonl {
+        let plugin = CommandSystemJsonlPlugin::new(log_options);
+        let tails = plugin.tails();
+        run_with_plugin(config, plugin, tails).await
+    } else {
+        let plugin = CommandSystemLogPlugin::new(log_options);
+        let tails = plugin.tails();
+        run_with_plugin(config, plugin, tails).await
+    }
+}
+
+async fn run_with_plugin<P>(
+    config: RunConfig,
+    plugin: P,
+    tails: OutputTails,
+) -> io::Result<CommandSystemSimpleReport>
+where
+    P: CommandSystemPlugin<LabeledCommandData, CommandInitialData = LabeledCommandData>,
+{
+    let colorize = config.color.should_colorize();
+    let reports = config.reports.clone();
+
+    let mut system = crate::run::spawn_from_run_config_with_plugin(config, plugin);
 
     let killer = system.share_killer();
 
     tokio::spawn(async move {
         if let Err(err) = tokio::signal::ctrl_c().await {
-            eprintln!(
-                "[runcc][warning] failed to setup Ctrl-C signal handler: {}",
-                err
+            eprint_message(
+                colorize,
+                MessageLevel::Warning,
+                format_args!("failed to setup Ctrl-C signal handler: {}", err),
             );
         } else {
             killer.kill_all().await;
         }
     });
 
-    Ok(system.wait().await)
+    let report = system.wait_into_report().await;
+
+    write_reports(&reports, &report, &tails, colorize);
+
+    Ok(report.to_simple())
 }
@@ -0,0 +1,160 @@
+use crate::Encoding;
+
+/// Decodes output of a command lossily, chunk by chunk.
+///
+/// Bytes of a char split across chunks are kept until the next chunk.
+#[derive(Debug)]
+pub(super) struct Decoder {
+    encoding: Encoding,
+    /// Bytes not decoded yet at the end of the last chunk
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub(super) fn new(encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            pending: vec![],
+        }
+    }
+
+    /// Decode `bytes` and push chars to `out`
+    pub(super) fn decode(&mut self, bytes: &[u8], out: &mut String) {
+        match self.encoding {
+            Encoding::Latin1 => out.extend(bytes.iter().map(|b| *b as char)),
+            Encoding::Utf8 => {
+                self.pending.extend_from_slice(bytes);
+                self.decode_utf8(out);
+            }
+            Encoding::Utf16 | Encoding::Utf16le | Encoding::Utf16be => {
+                self.pending.extend_from_slice(bytes);
+                self.decode_utf16(out);
+            }
+        }
+    }
+
+    /// Push the replacement char for incomplete bytes at the end of output
+    pub(super) fn finish(&mut self, out: &mut String) {
+        if !self.pending.is_empty() {
+            self.pending.clear();
+            out.push(char::REPLACEMENT_CHARACTER);
+        }
+    }
+
+    fn decode_utf8(&mut self, out: &mut String) {
+        let mut rest = &self.pending[..];
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    out.push_str(s);
+                    rest = &[];
+                    break;
+                }
+                Err(err) => {
+                    let (valid, after) = rest.split_at(err.valid_up_to());
+                    out.push_str(std::str::from_utf8(valid).unwrap());
+
+                    match err.error_len() {
+                        Some(len) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        // incomplete char at the end
+                        None => {
+                            rest = after;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        let len = rest.len();
+        self.pending.drain(..(self.pending.len() - len));
+    }
+
+    fn decode_utf16(&mut self, out: &mut String) {
+        if self.encoding == Encoding::Utf16 {
+            match self.pending.get(..2) {
+                Some([0xFF, 0xFE]) => {
+                    self.encoding = Encoding::Utf16le;
+                    self.pending.drain(..2);
+                }
+                Some([0xFE, 0xFF]) => {
+                    self.encoding = Encoding::Utf16be;
+                    self.pending.drain(..2);
+                }
+                Some(_) => self.encoding = Encoding::Utf16le,
+                None => return,
+            }
+        }
+
+        let big_endian = self.encoding == Encoding::Utf16be;
+        let mut units: Vec<u16> = self
+            .pending
+            .chunks_exact(2)
+            .map(|b| {
+                if big_endian {
+                    u16::from_be_bytes([b[0], b[1]])
+                } else {
+                    u16::from_le_bytes([b[0], b[1]])
+                }
+            })
+            .collect();
+
+        // keep a high surrogate at the end, which may be paired with the next unit
+        let mut keep = self.pending.len() % 2;
+        if units
+            .last()
+            .is_some_and(|unit| (0xD800..0xDC00).contains(unit))
+        {
+            units.pop();
+            keep += 2;
+        }
+
+        out.extend(char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)));
+
+        self.pending.drain(..(self.pending.len() - keep));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decoder;
+    use crate::Encoding;
+
+    fn decode(encoding: Encoding, chunks: &[&[u8]]) -> String {
+        let mut decoder = Decoder::new(encoding);
+        let mut out = String::new();
+        for chunk in chunks {
+            decoder.decode(chunk, &mut out);
+        }
+        decoder.finish(&mut out);
+        out
+    }
+
+    #[test]
+    fn decode_utf8_lossy() {
+        assert_eq!(
+            decode(Encoding::Utf8, &[b"a\xE4\xBD", b"\xA0b\xFFc\xE4"]),
+            "a你b\u{FFFD}c\u{FFFD}"
+        );
+    }
+
+    #[test]
+    fn decode_latin1() {
+        assert_eq!(decode(Encoding::Latin1, &[b"caf\xE9"]), "café");
+    }
+
+    #[test]
+    fn decode_utf16() {
+        // "a😀" in UTF-16LE with BOM, split inside the surrogate pair
+        assert_eq!(
+            decode(Encoding::Utf16, &[b"\xFF\xFEa\x00\x3D", b"\xD8\x00\xDE"]),
+            "a😀"
+        );
+        assert_eq!(decode(Encoding::Utf16be, &[b"\x00a\x00"]), "a\u{FFFD}");
+        assert_eq!(decode(Encoding::Utf16le, &[b"\x00\xD8a\x00"]), "\u{FFFD}a");
+    }
+}
@@ -1,19 +1,31 @@
 use std::fmt::Display;
 
+use super::{PrefixTemplateError, TimestampFormatError};
 use crate::read::error::FindConfigError;
 
 #[derive(Debug)]
 pub enum OptionsError {
     ConfigFileError(FindConfigError),
     EnvSyntaxError(String),
+    EnvFileError(std::io::Error),
     DuplicateConfigs,
     NoConfigs,
+    InvalidPrefix(PrefixTemplateError),
+    InvalidTimestampFormat(TimestampFormatError),
+    InvalidRegex(regex::Error),
+    /// `stdout` or `stderr` of the command with this label is `file`,
+    /// but neither `log_file` nor `log_dir` is set
+    MissingLogFile(String),
 }
 
 impl std::error::Error for OptionsError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             OptionsError::ConfigFileError(err) => Some(err),
+            OptionsError::EnvFileError(err) => Some(err),
+            OptionsError::InvalidPrefix(err) => Some(err),
+            OptionsError::InvalidTimestampFormat(err) => Some(err),
+            OptionsError::InvalidRegex(err) => Some(err),
             _ => None,
         }
     }
@@ -26,6 +38,7 @@ impl Display for OptionsError {
             OptionsError::EnvSyntaxError(env) => {
                 write!(f, "The following env var has invalid syntax: {}", env)
             }
+            OptionsError::EnvFileError(err) => write!(f, "Env file error: {}", err),
             OptionsError::DuplicateConfigs => {
                 write!(
                     f,
@@ -38,6 +51,16 @@ impl Display for OptionsError {
                     "Please specify commands from config file or positional arguments"
                 )
             }
+            OptionsError::InvalidPrefix(err) => write!(f, "Invalid prefix: {}", err),
+            OptionsError::InvalidTimestampFormat(err) => {
+                write!(f, "Invalid timestamp format: {}", err)
+            }
+            OptionsError::InvalidRegex(err) => write!(f, "Invalid regex: {}", err),
+            OptionsError::MissingLogFile(label) => write!(
+                f,
+                "Output of command {:?} is written to file, but neither log_file nor log_dir is set",
+                label
+            ),
         }
     }
 }
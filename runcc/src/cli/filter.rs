@@ -0,0 +1,180 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use super::log_file::strip_ansi_escapes;
+use crate::{Color, OutputRules};
+
+/// Compiled [`OutputRules`] of a command and the global `grep`
+#[derive(Debug, Clone, Default)]
+pub(super) struct OutputFilter {
+    hide: Vec<Regex>,
+    only: Vec<Regex>,
+    grep: Option<Regex>,
+    highlight: Vec<(Regex, Color)>,
+}
+
+fn compile_all(regexes: Option<&Vec<String>>) -> Result<Vec<Regex>, regex::Error> {
+    regexes
+        .into_iter()
+        .flatten()
+        .map(|regex| Regex::new(regex))
+        .collect()
+}
+
+impl OutputFilter {
+    pub(super) fn new(
+        rules: Option<&OutputRules>,
+        grep: Option<&Regex>,
+    ) -> Result<Self, regex::Error> {
+        let default = OutputRules::default();
+        let rules = rules.unwrap_or(&default);
+
+        Ok(Self {
+            hide: compile_all(rules.hide.as_ref())?,
+            only: compile_all(rules.only.as_ref())?,
+            grep: grep.cloned(),
+            highlight: rules
+                .highlight
+                .iter()
+                .flatten()
+                .map(|rule| {
+                    let color = rule.color.unwrap_or(Color::YELLOW);
+                    Regex::new(&rule.regex).map(|regex| (regex, color))
+                })
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Whether `line` should be printed.
+    ///
+    /// A line is hidden if it matches any `hide` regex,
+    /// or doesn't match any `only` regex, or doesn't match `grep`.
+    pub(super) fn is_shown(&self, line: &str) -> bool {
+        if self.hide.is_empty() && self.only.is_empty() && self.grep.is_none() {
+            return true;
+        }
+
+        let line = strip_ansi_escapes(line);
+        let is_match = |regex: &Regex| regex.is_match(&line);
+
+        !self.hide.iter().any(is_match)
+            && (self.only.is_empty() || self.only.iter().any(is_match))
+            && self.grep.as_ref().is_none_or(is_match)
+    }
+
+    /// Paint matches of `highlight` regexes in their colors.
+    ///
+    /// Since painting resets styles after each match,
+    /// styles of the line active at the end of the match are written again.
+    pub(super) fn highlight<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut line = Cow::Borrowed(line);
+        for (regex, color) in &self.highlight {
+            let current: &str = &line;
+            let highlighted = regex.replace_all(current, |caps: &regex::Captures| {
+                let m = caps.get(0).expect("group 0 always matches");
+                color.paint(m.as_str()) + &active_sgr(&current[..m.end()])
+            });
+            if let Cow::Owned(highlighted) = highlighted {
+                line = Cow::Owned(highlighted);
+            }
+        }
+        line
+    }
+}
+
+/// SGR sequences like `\x1B[31m` which are active at the end of `s`,
+/// which are those after the last reset
+fn active_sgr(s: &str) -> String {
+    let mut active = String::new();
+    let mut rest = s;
+
+    while let Some(start) = rest.find("\x1B[") {
+        let after = &rest[(start + 2)..];
+        let params_len = after
+            .find(|c: char| !c.is_ascii_digit() && c != ';')
+            .unwrap_or(after.len());
+
+        if after[params_len..].starts_with('m') {
+            let params = &after[..params_len];
+            if params.is_empty() || params == "0" {
+                active.clear();
+            } else {
+                active.push_str(&rest[start..(start + 2 + params_len + 1)]);
+            }
+            rest = &after[(params_len + 1)..];
+        } else {
+            rest = after;
+        }
+    }
+
+    active
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::OutputFilter;
+    use crate::{HighlightRule, OutputRules};
+
+    fn strings(regexes: &[&str]) -> Option<Vec<String>> {
+        Some(regexes.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn filter_lines() {
+        let rules = OutputRules {
+            hide: strings(&["GET /health"]),
+            only: strings(&["^GET", "^POST"]),
+            highlight: None,
+        };
+        let filter = OutputFilter::new(Some(&rules), None).unwrap();
+
+        assert!(filter.is_shown("GET /users 200"));
+        assert!(filter.is_shown("\x1B[32mPOST\x1B[0m /users 201"));
+        assert!(!filter.is_shown("GET /health 200"));
+        assert!(!filter.is_shown("listening on :8080"));
+
+        let grep = Regex::new("users").unwrap();
+        let filter = OutputFilter::new(Some(&rules), Some(&grep)).unwrap();
+        assert!(filter.is_shown("GET /users 200"));
+        assert!(!filter.is_shown("GET /posts 200"));
+
+        let filter = OutputFilter::new(None, Some(&grep)).unwrap();
+        assert!(!filter.is_shown("listening on :8080"));
+    }
+
+    #[test]
+    fn highlight_matches() {
+        let rules = OutputRules {
+            highlight: Some(vec![HighlightRule {
+                regex: "ERROR|WARN".to_string(),
+                color: None,
+            }]),
+            ..Default::default()
+        };
+        let filter = OutputFilter::new(Some(&rules), None).unwrap();
+
+        assert_eq!(
+            filter.highlight("ERROR: disk full"),
+            "\x1B[33mERROR\x1B[0m: disk full"
+        );
+        assert_eq!(filter.highlight("ok"), "ok");
+
+        // styles of the command are kept after matches
+        assert_eq!(
+            filter.highlight("\x1B[31merror: ERROR \x1B[1mhere\x1B[0m, WARN"),
+            "\x1B[31merror: \x1B[33mERROR\x1B[0m\x1B[31m \x1B[1mhere\x1B[0m, \x1B[33mWARN\x1B[0m"
+        );
+    }
+
+    #[test]
+    fn invalid_regex() {
+        let rules = OutputRules {
+            hide: strings(&["("]),
+            ..Default::default()
+        };
+        assert!(OutputFilter::new(Some(&rules), None).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::timestamp::LineTime;
+use crate::GroupStyle;
+
+/// How a command ended, to render the header and footer of its output block
+pub struct GroupSummary<'a> {
+    /// Label to display, maybe colored
+    pub label: &'a str,
+    pub index: usize,
+    /// Like `exited with status code 0`
+    pub status: &'a str,
+    pub success: bool,
+    pub spawned_at: SystemTime,
+    pub exited_at: SystemTime,
+    pub duration: Duration,
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+impl GroupStyle {
+    /// Header and footer of an output block
+    pub fn render_frame(self, summary: &GroupSummary) -> (String, String) {
+        let title = format!(
+            "{} {} in {:.3}s",
+            summary.label,
+            summary.status,
+            summary.duration.as_secs_f64()
+        );
+
+        match self.resolve() {
+            GroupStyle::Github => (format!("::group::{}", title), "::endgroup::".to_string()),
+            GroupStyle::Gitlab => {
+                // https://docs.gitlab.com/ee/ci/jobs/#custom-collapsible-sections
+                let name = format!("runcc_{}", summary.index);
+                let collapsed = if summary.success {
+                    "[collapsed=true]"
+                } else {
+                    ""
+                };
+                (
+                    format!(
+                        "\x1B[0Ksection_start:{}:{}{}\r\x1B[0K{}",
+                        unix_secs(summary.spawned_at),
+                        name,
+                        collapsed,
+                        title
+                    ),
+                    format!(
+                        "\x1B[0Ksection_end:{}:{}\r\x1B[0K",
+                        unix_secs(summary.exited_at),
+                        name
+                    ),
+                )
+            }
+            _ => (
+                format!("===== {} =====", title),
+                format!("===== end of {} =====", summary.label),
+            ),
+        }
+    }
+}
+
+/// Buffered output of a command in grouped output mode.
+///
+/// The block is printed after the output is drained and the command exited,
+/// whichever comes last.
+pub struct OutputGroup {
+    pub spawned_at: LineTime,
+    lines: Vec<String>,
+    drained: bool,
+    frame: Option<(String, String)>,
+}
+
+impl OutputGroup {
+    pub fn new(spawned_at: LineTime) -> Self {
+        Self {
+            spawned_at,
+            lines: vec![],
+            drained: false,
+            frame: None,
+        }
+    }
+
+    pub fn push(&mut self, line: String) {
+        self.lines.push(line);
+    }
+
+    /// Mark stdout and stderr as drained,
+    /// and return the block to print if the command has exited
+    pub fn set_drained(&mut self) -> Option<String> {
+        self.drained = true;
+        self.take_block()
+    }
+
+    /// Set the header and footer after the command exited,
+    /// and return the block to print if output has been drained
+    pub fn set_exited(&mut self, frame: (String, String)) -> Option<String> {
+        self.frame = Some(frame);
+        self.take_block()
+    }
+
+    fn take_block(&mut self) -> Option<String> {
+        if !self.drained {
+            return None;
+        }
+
+        let (header, footer) = self.frame.take()?;
+        let lines = std::mem::take(&mut self.lines);
+
+        Some(render_block(&header, &lines, &footer))
+    }
+}
+
+pub fn render_block(header: &str, lines: &[String], footer: &str) -> String {
+    let mut block = String::from(header);
+    for line in lines {
+        block.push('\n');
+        block.push_str(line);
+    }
+    block.push('\n');
+    block.push_str(footer);
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GroupSummary, OutputGroup};
+    use crate::cli::LineTime;
+    use crate::GroupStyle;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn summary(success: bool) -> GroupSummary<'static> {
+        GroupSummary {
+            label: "[api]",
+            index: 2,
+            status: "exited with status code 0",
+            success,
+            spawned_at: UNIX_EPOCH + Duration::from_secs(100),
+            exited_at: UNIX_EPOCH + Duration::from_secs(102),
+            duration: Duration::from_millis(1500),
+        }
+    }
+
+    #[test]
+    fn render_group_frame() {
+        assert_eq!(
+            GroupStyle::Plain.render_frame(&summary(true)),
+            (
+                "===== [api] exited with status code 0 in 1.500s =====".to_string(),
+                "===== end of [api] =====".to_string()
+            )
+        );
+        assert_eq!(
+            GroupStyle::Github.render_frame(&summary(true)),
+            (
+                "::group::[api] exited with status code 0 in 1.500s".to_string(),
+                "::endgroup::".to_string()
+            )
+        );
+        assert_eq!(
+            GroupStyle::Gitlab.render_frame(&summary(false)),
+            (
+                "\x1B[0Ksection_start:100:runcc_2\r\x1B[0K[api] exited with status code 0 in 1.500s"
+                    .to_string(),
+                "\x1B[0Ksection_end:102:runcc_2\r\x1B[0K".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn print_group_when_drained_and_exited() {
+        let frame = || ("header".to_string(), "footer".to_string());
+
+        let mut group = OutputGroup::new(LineTime::now());
+        group.push("a".to_string());
+        assert_eq!(group.set_exited(frame()), None);
+        group.push("b".to_string());
+        assert_eq!(group.set_drained().as_deref(), Some("header\na\nb\nfooter"));
+
+        let mut group = OutputGroup::new(LineTime::now());
+        assert_eq!(group.set_drained(), None);
+        assert_eq!(group.set_exited(frame()).as_deref(), Some("header\nfooter"));
+    }
+}
@@ -0,0 +1,362 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+use tokio::io::AsyncRead;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::log::OutputStream;
+use super::reader::OutputEvent;
+use super::sink::OutputSink;
+use super::tail::OutputTails;
+use super::timestamp::format_rfc3339_utc;
+use super::{eprint_message, LogOptions, MessageLevel};
+use crate::run::{kill, CommandStopped, CommandSystemPlugin, ExitStatusReport, LabeledCommandData};
+use crate::OutputVisibility;
+
+/// An event printed as one line of JSON in jsonl output mode
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JsonlEvent<'a> {
+    /// A line of output of a command
+    Line {
+        timestamp: String,
+        label: &'a str,
+        index: usize,
+        /// `out` or `err`
+        stream: &'static str,
+        text: &'a str,
+        /// The line is not ended, but flushed because the command has been idle or exited
+        partial: bool,
+        /// The line continues a partial line
+        continued: bool,
+    },
+    Spawned {
+        timestamp: String,
+        label: &'a str,
+        index: usize,
+        pid: Option<u32>,
+    },
+    /// The command failed to spawn, and the error is in its `exited` event
+    SpawnFailed {
+        timestamp: String,
+        label: &'a str,
+        index: usize,
+    },
+    /// All commands are spawned or failed to spawn
+    AllSpawned {
+        timestamp: String,
+        spawned: usize,
+        failed_to_spawn: usize,
+    },
+    Exited {
+        timestamp: String,
+        label: &'a str,
+        index: usize,
+        success: bool,
+        /// Exit code, which is `None` if the command is terminated by a signal
+        status: Option<i32>,
+        /// Signal which terminated the command, only on unix
+        signal: Option<i32>,
+        /// Name of the signal like `SIGSEGV`
+        signal_name: Option<&'static str>,
+        core_dumped: bool,
+        /// Error when spawning or waiting for the command
+        error: Option<String>,
+        /// Why runcc killed or tried to kill the command
+        kill_reason: Option<String>,
+        kill_error: Option<String>,
+        /// Seconds since the command was spawned
+        duration: f64,
+    },
+    /// All commands exited
+    Summary {
+        timestamp: String,
+        total: usize,
+        success: usize,
+        failed: usize,
+        /// Seconds since runcc started
+        duration: f64,
+    },
+}
+
+impl JsonlEvent<'_> {
+    fn print(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => OutputStream::Stdout.write_line(&json),
+            Err(err) => eprint_message(
+                false,
+                MessageLevel::Error,
+                format_args!("failed to serialize event to json: {}", err),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct JsonlCounts {
+    spawned: usize,
+    failed_to_spawn: usize,
+    exited: usize,
+    success: usize,
+}
+
+/// Prints output lines and lifecycle events of commands as JSON Lines to stdout
+pub struct CommandSystemJsonlPlugin {
+    /// Tasks reading output of commands, by command index
+    readers: Mutex<HashMap<usize, JoinHandle<()>>>,
+    /// Tasks printing `exited` events
+    joins: Mutex<Vec<JoinHandle<()>>>,
+    /// Set to `true` after `all_spawned` is printed, which `exited` events wait for
+    all_spawned: watch::Sender<bool>,
+    /// Kept so that `all_spawned` can always be set
+    all_spawned_rx: watch::Receiver<bool>,
+    options: Arc<LogOptions>,
+    started_at: Instant,
+    /// When commands are spawned, by command index
+    spawned_at: Mutex<HashMap<usize, Instant>>,
+    counts: Mutex<JsonlCounts>,
+    /// Recent lines by command index, for report files
+    tails: OutputTails,
+}
+
+impl CommandSystemJsonlPlugin {
+    pub fn new(options: LogOptions) -> Self {
+        let (all_spawned, all_spawned_rx) = watch::channel(false);
+
+        Self {
+            readers: Default::default(),
+            joins: Default::default(),
+            all_spawned,
+            all_spawned_rx,
+            options: Arc::new(options),
+            started_at: Instant::now(),
+            spawned_at: Default::default(),
+            counts: Default::default(),
+            tails: Default::default(),
+        }
+    }
+
+    /// Recent lines of commands, which are kept after commands exit
+    pub(super) fn tails(&self) -> OutputTails {
+        self.tails.clone()
+    }
+}
+
+/// What is needed to print output lines of a command
+struct CommandJsonlContext {
+    label: String,
+    index: usize,
+    /// `highlight` rules of output rules are ignored, since lines are not colorized
+    sink: OutputSink,
+}
+
+impl CommandJsonlContext {
+    async fn print_lines<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        stream: OutputStream,
+        visibility: OutputVisibility,
+    ) {
+        self.sink
+            .read(reader, stream, visibility, |event| {
+                if let OutputEvent::Line(line) = event {
+                    JsonlEvent::Line {
+                        timestamp: format_rfc3339_utc(SystemTime::now()),
+                        label: &self.label,
+                        index: self.index,
+                        stream: stream.name(),
+                        text: &line.text,
+                        partial: line.partial,
+                        continued: line.continued,
+                    }
+                    .print();
+                }
+            })
+            .await;
+    }
+}
+
+impl CommandSystemPlugin<LabeledCommandData> for CommandSystemJsonlPlugin {
+    type CommandInitialData = LabeledCommandData;
+
+    fn initialize_command_data(
+        &self,
+        data: Self::CommandInitialData,
+        stdout: tokio::process::ChildStdout,
+        stderr: tokio::process::ChildStderr,
+    ) -> LabeledCommandData {
+        self.initialize_command_data_with_pid(data, None, stdout, stderr)
+    }
+
+    fn initialize_command_data_with_pid(
+        &self,
+        data: Self::CommandInitialData,
+        pid: Option<u32>,
+        stdout: tokio::process::ChildStdout,
+        stderr: tokio::process::ChildStderr,
+    ) -> LabeledCommandData {
+        let sink = OutputSink::new(&data, &self.options, &self.tails);
+        let label = data.label.label().to_string();
+
+        self.spawned_at
+            .lock()
+            .unwrap()
+            .insert(data.index, Instant::now());
+        self.counts.lock().unwrap().spawned += 1;
+
+        JsonlEvent::Spawned {
+            timestamp: format_rfc3339_utc(SystemTime::now()),
+            label: &label,
+            index: data.index,
+            pid,
+        }
+        .print();
+
+        let ctx = CommandJsonlContext {
+            label,
+            index: data.index,
+            sink,
+        };
+
+        let (stdout_visibility, stderr_visibility) = (data.stdout, data.stderr);
+        let reader = tokio::spawn(async move {
+            tokio::join!(
+                ctx.print_lines(stdout, OutputStream::Stdout, stdout_visibility),
+                ctx.print_lines(stderr, OutputStream::Stderr, stderr_visibility),
+            );
+        });
+
+        let mut readers = self.readers.lock().unwrap();
+        readers.insert(data.index, reader);
+
+        data
+    }
+
+    fn on_commands_spawned(&self) {
+        let counts = self.counts.lock().unwrap();
+
+        JsonlEvent::AllSpawned {
+            timestamp: format_rfc3339_utc(SystemTime::now()),
+            spawned: counts.spawned,
+            failed_to_spawn: counts.failed_to_spawn,
+        }
+        .print();
+
+        let _ = self.all_spawned.send(true);
+    }
+
+    fn on_command_exited(&self, cmd: Arc<CommandStopped<LabeledCommandData, LabeledCommandData>>) {
+        let spawned_at = self.spawned_at.lock().unwrap().remove(&cmd.data.index);
+        let duration = spawned_at.map_or(0.0, |at| at.elapsed().as_secs_f64());
+
+        let (status, error) = match &cmd.exit_status {
+            Ok(status) => (Some(ExitStatusReport::from(*status)), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let success = status.is_some_and(|status| status.success);
+
+        let (kill_reason, kill_error) = match &cmd.killed {
+            Some(kill::KillJoinHandleFinalStatus::Killed(reason)) => {
+                (Some(reason.to_string()), None)
+            }
+            Some(kill::KillJoinHandleFinalStatus::FailedToKill { reason, error }) => {
+                (Some(reason.to_string()), Some(error.to_string()))
+            }
+            _ => (None, None),
+        };
+
+        {
+            let mut counts = self.counts.lock().unwrap();
+            counts.exited += 1;
+            if success {
+                counts.success += 1;
+            }
+        }
+
+        let timestamp = format_rfc3339_utc(SystemTime::now());
+        let reader = self.readers.lock().unwrap().remove(&cmd.data.index);
+        let mut all_spawned = self.all_spawned_rx.clone();
+
+        let join = tokio::spawn(async move {
+            // after `all_spawned`
+            while !*all_spawned.borrow() {
+                if all_spawned.changed().await.is_err() {
+                    break;
+                }
+            }
+
+            // after the last lines of this command
+            if let Some(reader) = reader {
+                let _ = reader.await;
+            }
+
+            JsonlEvent::Exited {
+                timestamp,
+                label: cmd.data.label.label(),
+                index: cmd.data.index,
+                success,
+                status: status.and_then(|status| status.code),
+                signal: status.and_then(|status| status.signal),
+                signal_name: status.and_then(|status| status.signal_name),
+                core_dumped: status.is_some_and(|status| status.core_dumped),
+                error,
+                kill_reason,
+                kill_error,
+                duration,
+            }
+            .print();
+        });
+
+        self.joins.lock().unwrap().push(join);
+    }
+
+    fn join(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let mut joins: Vec<_> = self.joins.lock().unwrap().drain(0..).collect();
+        joins.extend(
+            self.readers
+                .lock()
+                .unwrap()
+                .drain()
+                .map(|(_, reader)| reader),
+        );
+
+        let (total, success) = {
+            let counts = self.counts.lock().unwrap();
+            (counts.exited, counts.success)
+        };
+        let started_at = self.started_at;
+
+        Some(tokio::spawn(async move {
+            for join in joins.iter_mut() {
+                let _ = join.await;
+            }
+
+            JsonlEvent::Summary {
+                timestamp: format_rfc3339_utc(SystemTime::now()),
+                total,
+                success,
+                failed: total - success,
+                duration: started_at.elapsed().as_secs_f64(),
+            }
+            .print();
+        }))
+    }
+
+    fn initialize_spawn_failed_command_data(
+        &self,
+        data: Self::CommandInitialData,
+    ) -> LabeledCommandData {
+        self.counts.lock().unwrap().failed_to_spawn += 1;
+
+        JsonlEvent::SpawnFailed {
+            timestamp: format_rfc3339_utc(SystemTime::now()),
+            label: data.label.label(),
+            index: data.index,
+        }
+        .print();
+
+        data
+    }
+}
@@ -1,15 +1,313 @@
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::io::{IsTerminal, Write};
 use std::sync::{Arc, Mutex};
-use tokio::io::AsyncBufReadExt;
-use tokio::{io::BufReader, task::JoinHandle};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncRead;
+use tokio::task::JoinHandle;
 
-use crate::run::{kill, CommandStopped, CommandSystemPlugin, LabeledCommandData};
+use super::filter::OutputFilter;
+use super::group::{render_block, GroupSummary, OutputGroup};
+use super::prefix::{PrefixData, PrefixTemplate};
+use super::progress::ProgressLine;
+use super::reader::OutputEvent;
+use super::sink::OutputSink;
+use super::summary::{describe_status, format_summary_time, render_summary_table, SummaryRow};
+use super::tail::{FailedCommand, FailureSummary, OutputTails};
+use super::timestamp::{LineTime, TimestampFormat};
+use super::OptionsError;
+use crate::run::{CommandStopped, CommandSystemPlugin, ExitStatusReport, LabeledCommandData};
+use crate::{
+    Color, GroupStyle, LabelStyle, LogFileOptions, OutputMode, OutputVisibility, RunConfig,
+};
 
-pub struct CommandSystemLogPlugin(Mutex<Vec<JoinHandle<()>>>);
+#[derive(Debug, Clone, Copy)]
+pub enum MessageLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Print a message of runcc itself to stderr, like `[runcc][info] message`.
+///
+/// If `colorize` is true, the prefix is bold and colored by `level`,
+/// so that it looks different from labels of commands.
+/// Errors like a closed pipe are ignored.
+pub fn eprint_message(colorize: bool, level: MessageLevel, message: impl Display) {
+    let (level, color) = match level {
+        MessageLevel::Info => ("info", Color::BRIGHT_BLACK),
+        MessageLevel::Warning => ("warning", Color::YELLOW),
+        MessageLevel::Error => ("error", Color::RED),
+    };
+
+    let prefix = format!("[runcc][{}]", level);
+    let line = if colorize {
+        format!("\x1B[1m{} {}", color.paint(&prefix), message)
+    } else {
+        format!("{} {}", prefix, message)
+    };
+    OutputStream::Stderr.write_line(&line);
+}
+
+/// Options of [`CommandSystemLogPlugin`]
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    pub colorize: bool,
+    pub prefix: PrefixTemplate,
+    /// How labels are decorated in messages of runcc, like `[api] exited`
+    pub label_style: LabelStyle,
+    /// Format of `{time}` in `prefix`
+    pub timestamp: Option<TimestampFormat>,
+    pub log_file_options: LogFileOptions,
+    pub output: OutputMode,
+    /// Resolved style of output blocks in grouped output mode
+    pub group_style: GroupStyle,
+    /// Show lines being written in place, like progress bars.
+    ///
+    /// Only enabled in interleaved output mode when stdout is a terminal.
+    pub show_progress: bool,
+    /// Flush a line which is not ended after no output is read for this duration
+    pub partial_line_timeout: Option<Duration>,
+    /// Output rules of each command by index, with `grep` of [`RunConfig`]
+    pub output_filters: Vec<OutputFilter>,
+    /// Lines of output to print for each failed command at shutdown, `0` to disable
+    pub tail_on_failure: usize,
+    /// Print a table of all commands when all commands exit
+    pub show_summary: bool,
+}
+
+impl LogOptions {
+    /// Default of [`RunConfig::partial_line_timeout`] in milliseconds
+    pub const DEFAULT_PARTIAL_LINE_TIMEOUT: u64 = 500;
+    /// Default of [`RunConfig::tail_on_failure`]
+    pub const DEFAULT_TAIL_ON_FAILURE: usize = 10;
+
+    pub fn from_config(config: &RunConfig) -> Result<Self, OptionsError> {
+        let mut prefix = match &config.prefix {
+            Some(prefix) => PrefixTemplate::parse(prefix).map_err(OptionsError::InvalidPrefix)?,
+            None => PrefixTemplate::from_label_style(config.label_style),
+        };
+
+        if config.timestamps.is_some() {
+            prefix = prefix.with_time();
+        }
+
+        let timestamp = if prefix.has_time() {
+            let format = TimestampFormat::parse(
+                config.timestamps.unwrap_or_default(),
+                config
+                    .timestamp_format
+                    .as_deref()
+                    .unwrap_or(TimestampFormat::DEFAULT),
+            )
+            .map_err(OptionsError::InvalidTimestampFormat)?;
+
+            Some(format)
+        } else {
+            None
+        };
+
+        let grep = config
+            .grep
+            .as_deref()
+            .map(regex::Regex::new)
+            .transpose()
+            .map_err(OptionsError::InvalidRegex)?;
+        let output_filters = config
+            .commands
+            .iter()
+            .map(|cmd| OutputFilter::new(cmd.output.as_ref(), grep.as_ref()))
+            .collect::<Result<_, _>>()
+            .map_err(OptionsError::InvalidRegex)?;
+
+        Ok(Self {
+            colorize: config.color.should_colorize(),
+            prefix,
+            label_style: config.label_style,
+            timestamp,
+            log_file_options: config.log_file_options.clone(),
+            output: config.output,
+            group_style: config.group_style.resolve(),
+            show_progress: config.show_progress
+                && config.output == OutputMode::Interleaved
+                && std::io::stdout().is_terminal(),
+            partial_line_timeout: match config
+                .partial_line_timeout
+                .unwrap_or(Self::DEFAULT_PARTIAL_LINE_TIMEOUT)
+            {
+                0 => None,
+                ms => Some(Duration::from_millis(ms)),
+            },
+            output_filters,
+            tail_on_failure: config
+                .tail_on_failure
+                .unwrap_or(Self::DEFAULT_TAIL_ON_FAILURE),
+            show_summary: config.show_summary.unwrap_or(true),
+        })
+    }
+}
+
+pub struct CommandSystemLogPlugin {
+    joins: Mutex<Vec<JoinHandle<()>>>,
+    options: Arc<LogOptions>,
+    started_at: Instant,
+    /// Output groups by command index in grouped output mode
+    groups: Mutex<HashMap<usize, Arc<Mutex<OutputGroup>>>>,
+    progress: Arc<ProgressLine>,
+    /// Recent lines by command index
+    tails: OutputTails,
+    /// Commands which have exited and failed
+    failures: Arc<Mutex<FailureSummary>>,
+    /// Rows of the summary table of exited commands
+    summary: Arc<Mutex<Vec<SummaryRow>>>,
+}
 
 impl CommandSystemLogPlugin {
-    pub fn new() -> Self {
-        Self(Default::default())
+    pub fn new(options: LogOptions) -> Self {
+        Self {
+            joins: Default::default(),
+            progress: Arc::new(ProgressLine::new(options.show_progress)),
+            options: Arc::new(options),
+            started_at: Instant::now(),
+            groups: Default::default(),
+            tails: Default::default(),
+            failures: Default::default(),
+            summary: Default::default(),
+        }
+    }
+
+    /// Recent lines of commands, which are kept after commands exit
+    pub(super) fn tails(&self) -> OutputTails {
+        self.tails.clone()
+    }
+
+    /// Label decorated in `label_style`, in the color of the command if output is colorized
+    fn format_label(&self, data: &LabeledCommandData) -> String {
+        let label = self.options.label_style.wrap(data.label.display());
+        if self.options.colorize {
+            data.color.paint(&label)
+        } else {
+            label
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "out",
+            OutputStream::Stderr => "err",
+        }
+    }
+
+    /// Write a line to stdout or stderr of runcc.
+    ///
+    /// Errors like a closed pipe are ignored, so that output of commands is still drained.
+    pub(super) fn write_line(self, line: &str) {
+        let _ = match self {
+            OutputStream::Stdout => writeln!(std::io::stdout().lock(), "{}", line),
+            OutputStream::Stderr => writeln!(std::io::stderr().lock(), "{}", line),
+        };
+    }
+}
+
+/// Read and discard output of a hidden stream, so that the command doesn't block on a full pipe
+pub(super) async fn drain<R: AsyncRead + Unpin>(mut reader: R) {
+    let _ = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await;
+}
+
+/// What is needed to print output lines of a command
+struct CommandLogContext {
+    label: String,
+    index: usize,
+    pid: Option<u32>,
+    /// `None` if output is not colorized
+    color: Option<Color>,
+    sink: OutputSink,
+    /// When runcc started
+    started_at: Instant,
+    spawned_at: Instant,
+    options: Arc<LogOptions>,
+    /// Buffered output in grouped output mode
+    group: Option<Arc<Mutex<OutputGroup>>>,
+    progress: Arc<ProgressLine>,
+}
+
+impl CommandLogContext {
+    /// Format a line with the prefix.
+    ///
+    /// If the line continues a partial line printed before, `+` is appended to the prefix.
+    fn format_line(
+        &self,
+        stream: OutputStream,
+        line: &str,
+        time: &LineTime,
+        continued: bool,
+    ) -> String {
+        let options = &*self.options;
+        if options.prefix.is_none() {
+            return line.to_string();
+        }
+
+        let formatted_time = options
+            .timestamp
+            .as_ref()
+            .map(|format| format.format(time, self.started_at))
+            .unwrap_or_default();
+
+        let mut prefix = options.prefix.render(&PrefixData {
+            time: &formatted_time,
+            label: &self.label,
+            index: self.index,
+            pid: self.pid,
+            stream: stream.name(),
+            elapsed: time.instant.saturating_duration_since(self.spawned_at),
+        });
+
+        if continued {
+            prefix.push('+');
+        }
+
+        match self.color {
+            Some(color) => format!("{} {}", color.paint(&prefix), line),
+            None => format!("{} {}", prefix, line),
+        }
+    }
+
+    async fn print_lines<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        stream: OutputStream,
+        visibility: OutputVisibility,
+    ) {
+        self.sink
+            .read(reader, stream, visibility, |event| match event {
+                OutputEvent::Line(line) => {
+                    let time = LineTime::now();
+                    let text = match self.color {
+                        Some(_) => self.sink.filter.highlight(&line.text),
+                        None => line.text.as_str().into(),
+                    };
+                    let line = self.format_line(stream, &text, &time, line.continued);
+                    match &self.group {
+                        Some(group) => group.lock().unwrap().push(line),
+                        None => self.progress.print_line(self.index, stream, &line),
+                    }
+                }
+                OutputEvent::Progress(progress) => {
+                    if self.group.is_none() {
+                        let progress = self.format_line(stream, &progress, &LineTime::now(), false);
+                        self.progress.update(self.index, &progress);
+                    }
+                }
+            })
+            .await;
     }
 }
 
@@ -22,117 +320,158 @@ impl CommandSystemPlugin<LabeledCommandData> for CommandSystemLogPlugin {
         stdout: tokio::process::ChildStdout,
         stderr: tokio::process::ChildStderr,
     ) -> LabeledCommandData {
-        let label = data.label.display().to_string();
+        self.initialize_command_data_with_pid(data, None, stdout, stderr)
+    }
 
+    fn initialize_command_data_with_pid(
+        &self,
+        data: Self::CommandInitialData,
+        pid: Option<u32>,
+        stdout: tokio::process::ChildStdout,
+        stderr: tokio::process::ChildStderr,
+    ) -> LabeledCommandData {
+        let sink = OutputSink::new(&data, &self.options, &self.tails);
+
+        let spawned_at = LineTime::now();
+
+        let group = if self.options.output == OutputMode::Grouped {
+            let group = Arc::new(Mutex::new(OutputGroup::new(spawned_at)));
+            let mut groups = self.groups.lock().unwrap();
+            groups.insert(data.index, group.clone());
+            Some(group)
+        } else {
+            None
+        };
+
+        let ctx = CommandLogContext {
+            label: data.label.display().to_string(),
+            index: data.index,
+            pid,
+            sink,
+            color: if self.options.colorize {
+                Some(data.color)
+            } else {
+                None
+            },
+            started_at: self.started_at,
+            spawned_at: spawned_at.instant,
+            options: self.options.clone(),
+            group,
+            progress: self.progress.clone(),
+        };
+
+        let (stdout_visibility, stderr_visibility) = (data.stdout, data.stderr);
         let join = tokio::spawn(async move {
             tokio::join!(
-                async {
-                    let stdout = BufReader::new(stdout);
-                    let mut lines = stdout.lines();
-                    loop {
-                        match lines.next_line().await {
-                            Ok(line) => {
-                                if let Some(line) = line {
-                                    #[cfg(feature = "auto_ansi_escape")]
-                                    let line = crate::ansi_escape::process_ansi_escape_line(
-                                        label.len() + 3,
-                                        &line,
-                                    );
-
-                                    let line = format!("[{}] {}", label, line);
-                                    println!("{}", line);
-                                } else {
-                                    break;
-                                }
-                            }
-                            Err(err) => {
-                                eprintln!(
-                                    "[runcc error] failed to read line from [{}] stdout: {}",
-                                    label, err
-                                );
-                                break;
-                            }
-                        }
-                    }
-                },
-                async {
-                    let stderr = BufReader::new(stderr);
-                    let mut lines = stderr.lines();
-                    loop {
-                        match lines.next_line().await {
-                            Ok(line) => {
-                                if let Some(line) = line {
-                                    #[cfg(feature = "auto_ansi_escape")]
-                                    let line = crate::ansi_escape::process_ansi_escape_line(
-                                        label.len() + 3,
-                                        &line,
-                                    );
-
-                                    let line = format!("[{}] {}", label, line);
-                                    eprintln!("{}", line);
-                                } else {
-                                    break;
-                                }
-                            }
-                            Err(err) => {
-                                eprintln!(
-                                    "[runcc error] failed to read line from [{}] stderr: {}",
-                                    label, err
-                                );
-                                break;
-                            }
-                        }
-                    }
-                }
+                ctx.print_lines(stdout, OutputStream::Stdout, stdout_visibility),
+                ctx.print_lines(stderr, OutputStream::Stderr, stderr_visibility),
             );
+
+            ctx.progress.clear(ctx.index);
+
+            if let Some(group) = &ctx.group {
+                let block = group.lock().unwrap().set_drained();
+                if let Some(block) = block {
+                    OutputStream::Stdout.write_line(&block);
+                }
+            }
         });
 
-        let mut joins = self.0.lock().unwrap();
+        let mut joins = self.joins.lock().unwrap();
         joins.push(join);
 
         data
     }
 
     fn on_command_exited(&self, cmd: Arc<CommandStopped<LabeledCommandData, LabeledCommandData>>) {
-        let label = cmd.data.label.display();
-        let status = &cmd.exit_status;
-        let killed = &cmd.killed;
-        let status = match status {
-            Ok(s) => format!(
-                "code {}",
-                s.code()
-                    .map_or_else(|| "None".to_string(), |code| format!("{}", code))
-            ),
-            Err(err) => format!("error: {}", err),
-        };
+        let label = self.format_label(&cmd.data);
+        let status = describe_exit(&cmd);
+        let success = cmd.exit_status.as_ref().is_ok_and(|s| s.success());
+        // commands killed by runcc, like by Ctrl-C or kill_others, are counted apart from failures
+        let killed_by_runcc = matches!(
+            cmd.killed,
+            Some(crate::run::kill::KillJoinHandleFinalStatus::Killed(_))
+        );
 
-        let killed: std::borrow::Cow<str> = match killed {
-            Some(kill_status) => {
-                use crate::run::kill::KillJoinHandleFinalStatus as KS;
-                match kill_status {
-                    KS::Killed(reason) => format!(" (killed due to {})", reason).into(),
-                    KS::FailedToKill { reason, error } => {
-                        format!(" (tried to kill due to {} but failed: {})", reason, error).into()
-                    }
-                    _ => "".into(),
-                }
+        if self.options.show_summary {
+            self.summary.lock().unwrap().push(SummaryRow {
+                index: cmd.data.index,
+                label: cmd.data.label.display().trim().to_string(),
+                status: describe_status(&cmd.exit_status, cmd.spawned_at.is_some()),
+                killed: describe_kill_reason(&cmd),
+                started: cmd.spawned_at.map(format_summary_time),
+                ended: format_summary_time(cmd.exited_at),
+                duration: cmd.duration,
+                peak_memory: cmd.peak_memory,
+            });
+        }
+
+        {
+            let mut summary = self.failures.lock().unwrap();
+            summary.exited += 1;
+            if !success && self.options.tail_on_failure > 0 {
+                summary.failures.push(FailedCommand {
+                    label: label.clone(),
+                    status: status.clone(),
+                    killed: killed_by_runcc,
+                    tail: self.tails.get(cmd.data.index),
+                });
             }
-            None => "".into(),
+        }
+
+        if self.options.output != OutputMode::Grouped {
+            OutputStream::Stderr.write_line(&format!("{} {}", label, status));
+            return;
+        }
+
+        let exited_at = LineTime::now();
+        let group = self.groups.lock().unwrap().remove(&cmd.data.index);
+        // commands failed to spawn have no output
+        let spawned_at = group
+            .as_ref()
+            .map_or(exited_at, |group| group.lock().unwrap().spawned_at);
+
+        let frame = self.options.group_style.render_frame(&GroupSummary {
+            label: &label,
+            index: cmd.data.index,
+            status: &status,
+            success,
+            spawned_at: spawned_at.system,
+            exited_at: exited_at.system,
+            duration: exited_at
+                .instant
+                .saturating_duration_since(spawned_at.instant),
+        });
+
+        let block = match group {
+            Some(group) => group.lock().unwrap().set_exited(frame),
+            None => Some(render_block(&frame.0, &[], &frame.1)),
         };
 
-        let line = format!("[{}] exited with status {}{}", label, status, killed);
-        eprintln!("{}", line);
+        if let Some(block) = block {
+            OutputStream::Stdout.write_line(&block);
+        }
     }
 
     fn join(&self) -> Option<tokio::task::JoinHandle<()>> {
-        let mut joins = self.0.lock().unwrap();
+        let mut joins = self.joins.lock().unwrap();
 
         let mut joins: Vec<_> = joins.drain(0..).collect();
+        let failures = self.failures.clone();
+        let summary = self.summary.clone();
+        let colorize = self.options.colorize;
 
         Some(tokio::spawn(async move {
             for join in joins.iter_mut() {
                 let _ = join.await;
             }
+
+            // after all output is read
+            let mut summary = summary.lock().unwrap();
+            if !summary.is_empty() {
+                OutputStream::Stderr.write_line(&render_summary_table(&mut summary));
+            }
+            failures.lock().unwrap().print(colorize);
         }))
     }
 
@@ -144,13 +483,40 @@ impl CommandSystemPlugin<LabeledCommandData> for CommandSystemLogPlugin {
     }
 }
 
-impl Display for kill::KillCommandReason<LabeledCommandData> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            kill::KillCommandReason::OtherCommandExited(cmd) => {
-                write!(f, "command[{}] exited", cmd.data.label.label())
+/// Like `exited with status code 0`
+fn describe_exit(cmd: &CommandStopped<LabeledCommandData, LabeledCommandData>) -> String {
+    let status = &cmd.exit_status;
+    let killed = &cmd.killed;
+    let status = match status {
+        Ok(s) => ExitStatusReport::from(*s).to_string(),
+        Err(err) => format!("error: {}", err),
+    };
+
+    let killed: std::borrow::Cow<str> = match killed {
+        Some(kill_status) => {
+            use crate::run::kill::KillJoinHandleFinalStatus as KS;
+            match kill_status {
+                KS::Killed(reason) => format!(" (killed due to {})", reason).into(),
+                KS::FailedToKill { reason, error } => {
+                    format!(" (tried to kill due to {} but failed: {})", reason, error).into()
+                }
+                _ => "".into(),
             }
-            kill::KillCommandReason::MainProcessGotSignal => write!(f, "Ctrl-C signal"),
         }
+        None => "".into(),
+    };
+
+    format!("exited with status {}{}", status, killed)
+}
+
+/// Why runcc killed the command, `None` if it's not killed
+fn describe_kill_reason(
+    cmd: &CommandStopped<LabeledCommandData, LabeledCommandData>,
+) -> Option<String> {
+    use crate::run::kill::KillJoinHandleFinalStatus as KS;
+    match cmd.killed.as_ref()? {
+        KS::Killed(reason) => Some(reason.to_string()),
+        KS::FailedToKill { reason, .. } => Some(format!("{} (failed to kill)", reason)),
+        _ => None,
     }
 }
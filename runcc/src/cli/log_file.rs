@@ -0,0 +1,241 @@
+use std::{
+    borrow::Cow,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{LogFileMode, LogFileOptions};
+
+/// Remove ANSI escape sequences from `line`,
+/// including CSI sequences like colors and OSC sequences like hyperlinks.
+pub fn strip_ansi_escapes(line: &str) -> Cow<'_, str> {
+    if !line.contains('\x1B') {
+        return line.into();
+    }
+
+    let mut res = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1B' {
+            res.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            // CSI: parameters and intermediates end with a final byte in `@`..=`~`
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ends with BEL or ST (`ESC \`)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1B' {
+                        chars.next_if_eq(&'\\');
+                        break;
+                    }
+                }
+            }
+            // two-character sequences like `ESC (`
+            _ => {}
+        }
+    }
+
+    res.into()
+}
+
+/// Writes output lines of a command to a log file, rotating it by size
+pub struct LogFileWriter {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    options: LogFileOptions,
+}
+
+impl LogFileWriter {
+    pub fn open(path: PathBuf, options: LogFileOptions) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+
+        let file = match options.mode {
+            LogFileMode::Append => OpenOptions::new().create(true).append(true).open(&path)?,
+            LogFileMode::Truncate => File::create(&path)?,
+        };
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            file,
+            size,
+            options,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let line = if self.options.strip_ansi {
+            strip_ansi_escapes(line)
+        } else {
+            line.into()
+        };
+
+        let len = line.len() as u64 + 1;
+        if let Some(max_size) = self.options.max_size {
+            if self.size > 0 && self.size + len > max_size {
+                self.rotate()?;
+            }
+        }
+
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+
+        Ok(())
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    /// Rename `app.log.{n}` to `app.log.{n+1}` and `app.log` to `app.log.1`,
+    /// dropping files beyond `max_files`.
+    fn rotate(&mut self) -> io::Result<()> {
+        let max_files = self.options.max_files;
+
+        if max_files > 0 {
+            for n in (1..max_files).rev() {
+                match fs::rename(self.rotated_path(n), self.rotated_path(n + 1)) {
+                    Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                    _ => {}
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_ansi_escapes, LogFileWriter};
+    use crate::{LogFileMode, LogFileOptions};
+    use std::fs;
+
+    #[test]
+    fn strip_ansi() {
+        for (line, res) in [
+            ("plain", "plain"),
+            ("\x1B[1;31merror\x1B[0m: x", "error: x"),
+            ("\x1B[2K\x1B[1Gprogress", "progress"),
+            ("\x1B]8;;https://a.b\x07link\x1B]8;;\x1B\\", "link"),
+            ("你\x1B[32m好", "你好"),
+        ] {
+            assert_eq!(strip_ansi_escapes(line), res, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn rotate_log_file() {
+        let dir = std::env::temp_dir().join(format!("runcc-log-file-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("logs").join("app.log");
+
+        let options = LogFileOptions {
+            mode: LogFileMode::Truncate,
+            strip_ansi: true,
+            max_size: Some(8),
+            max_files: 2,
+        };
+
+        let mut writer = LogFileWriter::open(path.clone(), options).unwrap();
+        for line in ["\x1B[31m111\x1B[0m", "222", "333", "444"] {
+            writer.write_line(line).unwrap();
+        }
+
+        let read = |suffix: &str| {
+            let mut path = path.clone().into_os_string();
+            path.push(suffix);
+            fs::read_to_string(path).ok()
+        };
+
+        assert_eq!(read("").as_deref(), Some("333\n444\n"));
+        assert_eq!(read(".1").as_deref(), Some("111\n222\n"));
+        assert_eq!(read(".2"), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_appended_log_file() {
+        let dir = std::env::temp_dir().join(format!("runcc-log-append-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        fs::write(&path, "000\n").unwrap();
+        fs::write(dir.join("app.log.1"), "old\n").unwrap();
+
+        let options = LogFileOptions {
+            mode: LogFileMode::Append,
+            strip_ansi: false,
+            max_size: Some(8),
+            max_files: 1,
+        };
+
+        let mut writer = LogFileWriter::open(path.clone(), options).unwrap();
+        // the existing content counts towards max_size
+        for line in ["111", "222", "333"] {
+            writer.write_line(line).unwrap();
+        }
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+
+        assert_eq!(read("app.log").as_deref(), Some("222\n333\n"));
+        assert_eq!(read("app.log.1").as_deref(), Some("000\n111\n"));
+        assert_eq!(read("app.log.2"), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn truncate_without_rotated_files() {
+        let dir = std::env::temp_dir().join(format!("runcc-log-truncate-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("app.log");
+
+        let options = LogFileOptions {
+            mode: LogFileMode::Truncate,
+            strip_ansi: false,
+            max_size: Some(4),
+            max_files: 0,
+        };
+
+        let mut writer = LogFileWriter::open(path.clone(), options).unwrap();
+        for line in ["111", "222"] {
+            writer.write_line(line).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "222\n");
+        assert!(!dir.join("app.log.1").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
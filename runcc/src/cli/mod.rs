@@ -1,9 +1,27 @@
 mod app;
+mod decode;
 mod error;
+mod filter;
+mod group;
+mod jsonl;
 mod log;
+mod log_file;
 mod options;
+mod prefix;
+mod progress;
+mod reader;
+mod report;
+mod sink;
+mod summary;
+mod tail;
+mod timestamp;
 pub use app::*;
 pub use error::*;
+pub use group::*;
+pub use log_file::*;
 pub use options::*;
+pub use prefix::*;
+pub use timestamp::*;
 
-pub(self) use log::*;
+use jsonl::*;
+use log::*;
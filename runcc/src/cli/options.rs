@@ -2,8 +2,11 @@ use std::collections::HashMap;
 
 use clap::{AppSettings, Parser};
 
-use super::OptionsError;
-use crate::{read, KillBehavior, RunConfig};
+use super::{eprint_message, MessageLevel, OptionsError};
+use crate::{
+    read, ColorChoice, GroupStyle, KillBehavior, LabelAlign, LabelStyle, LabelTruncate, OutputMode,
+    OutputVisibility, ReportFile, RunConfig, TimestampMode,
+};
 
 /// Run commands concurrently
 #[derive(Parser)]
@@ -22,25 +25,174 @@ pub struct Opts {
     config: Option<Option<String>>,
     /// Max length to print label in logs
     ///
-    /// Defaults to the max length of all labels
+    /// Defaults to the max length of all labels.
+    /// Length is the display width in terminals, where CJK characters and emoji take 2 columns.
     #[clap(long)]
     max_label_length: Option<usize>,
+    /// How to truncate labels longer than --max-label-length: end (default), middle, last_segment
+    ///
+    /// end          : keep the start, like "cargo run -p ve..."
+    ///
+    /// middle       : keep the start and the end, like "cargo r...e-name"
+    ///
+    /// last_segment : keep the last segment separated by spaces or slashes,
+    ///                like "very-long-crate-name"
+    #[clap(long, value_name = "MODE")]
+    label_truncate: Option<LabelTruncate>,
+    /// How to align labels shorter than --max-label-length: left (default), right, center
+    #[clap(long, value_name = "ALIGN")]
+    label_align: Option<LabelAlign>,
+    /// How to decorate labels in the default prefix: brackets (default), pipe, none
+    ///
+    /// brackets : [api]
+    ///
+    /// pipe     : api |
+    ///
+    /// none     : api
+    #[clap(long, value_name = "STYLE")]
+    label_style: Option<LabelStyle>,
+    /// Show the index of each command before the label, like [2:api]
+    #[clap(long)]
+    show_index: bool,
     /// Specify env vars with K=V
     #[clap(short, long)]
     env: Vec<String>,
+    /// Load env vars from dotenv files
+    ///
+    /// Env vars are applied in the following order, later ones take precedence:
+    /// env files (including --env-file), envs (including -e),
+    /// env files of each command, envs of each command (including inline K=V).
+    /// ${VAR} in commands is resolved with the same precedence, then from env vars of runcc process.
+    /// Env files which don't exist are ignored.
+    #[clap(long)]
+    env_file: Vec<String>,
     /// What to do after some command exits
     ///
-    /// -k None (default)   : do nothing
+    /// -k None (default)       : do nothing
+    ///
+    /// -k WhenAnyExited        : kill all commands when any exited
+    ///
+    /// -k WhenAnySucceeded     : kill all commands when any exited with status == 0
+    ///
+    /// -k WhenAnyFailed        : kill all commands when any exited with status != 0
+    ///
+    /// -k WhenAnyKilledBySignal, WhenAnyCoreDumped, WhenAnySpawnFailed
     ///
-    /// -k WhenAnyExited    : kill all commands when any exited
+    /// -k <NUMBER>             : kill all commands when any exited with status == <NUMBER>
     ///
-    /// -k WhenAnySucceeded : kill all commands when any exited with status == 0
+    /// -k 1,2 or -k 2..=127    : kill all commands when any exited with one of these statuses
     ///
-    /// -k WhenAnyFailed    : kill all commands when any exited with status != 0
+    /// -k <SIGNAL>             : kill all commands when any was terminated by <SIGNAL> like SIGSEGV
     ///
-    /// -k <NUMBER>         : kill all commands when any exited with status == <NUMBER>
+    /// -k '!<PATTERN>'         : kill all commands when any exited not matching <PATTERN>, like !0,1
     #[clap(short, long)]
     kill: Option<KillBehavior>,
+    /// When to colorize output: auto, always, never
+    ///
+    /// Defaults to auto, which colorizes if stdout is a terminal and NO_COLOR is not set.
+    /// Labels of commands are colored when output is colorized.
+    /// Commands are told to colorize or not with env vars
+    /// like CARGO_TERM_COLOR, FORCE_COLOR and NO_COLOR.
+    #[clap(long, value_name = "WHEN")]
+    color: Option<ColorChoice>,
+    /// Template of the prefix of each output line
+    ///
+    /// Defaults to "[{label}]". Available fields:
+    /// {time}, {label}, {index}, {pid}, {stream} (out or err), {elapsed}.
+    /// Use {{ and }} for literal braces.
+    /// "none" prints output as is, without prefix.
+    #[clap(long, value_name = "TEMPLATE")]
+    prefix: Option<String>,
+    /// Add timestamps to output lines: wall (default), since_start
+    ///
+    /// wall        : local time when the line is read
+    ///
+    /// since_start : time since runcc started
+    ///
+    /// Timestamps are prepended to the prefix, unless {time} is in --prefix.
+    /// Use --timestamps=<MODE> to specify the mode.
+    #[clap(long, value_name = "MODE", require_equals = true)]
+    timestamps: Option<Option<TimestampMode>>,
+    /// Format of timestamps, defaults to "%T%.3f"
+    ///
+    /// Supports strftime specifiers like %F %T %z %s,
+    /// and %3f %6f %9f (or %.3f %.6f %.9f with a leading dot) for fractions of a second.
+    /// For time since start, %H is total hours and only time specifiers are allowed.
+    #[clap(long, value_name = "FORMAT")]
+    timestamp_format: Option<String>,
+    /// Write output of each command to {label}.log in this directory
+    ///
+    /// Output is written without prefix.
+    /// Commands with log_file in the config file are written to that file instead.
+    #[clap(long, value_name = "DIR")]
+    log_dir: Option<String>,
+    /// How to print output of commands: interleaved (default), grouped, jsonl
+    ///
+    /// interleaved : print lines as soon as they are read
+    ///
+    /// grouped     : print output of each command as one block when it exits,
+    ///               with a header and a footer showing label, status and duration.
+    ///               Both stdout and stderr of commands are printed to stdout.
+    ///
+    /// jsonl       : print one JSON object per line to stdout for each event,
+    ///               like output lines, spawns and exits of commands
+    #[clap(long, value_name = "MODE")]
+    output: Option<OutputMode>,
+    /// Style of output blocks in grouped output mode: auto (default), plain, github, gitlab
+    ///
+    /// auto uses github in GitHub Actions, gitlab in GitLab CI, and plain otherwise.
+    #[clap(long, value_name = "STYLE")]
+    group_style: Option<GroupStyle>,
+    /// Show lines being written in place at the bottom, like progress bars
+    ///
+    /// Only works in interleaved output mode when stdout is a terminal.
+    /// Otherwise, only final rendered lines are printed.
+    #[clap(long)]
+    show_progress: bool,
+    /// Flush a line which is not ended, like a prompt,
+    /// after no output is read for this duration in milliseconds
+    ///
+    /// Defaults to 500. 0 disables it, so such a line is printed when it ends.
+    /// Lines continuing a flushed line are marked with "+" after the prefix.
+    #[clap(long, value_name = "MS")]
+    partial_line_timeout: Option<u64>,
+    /// Only show output lines matching this regex, for all commands
+    ///
+    /// Lines are matched without colors, and still written to log files.
+    #[clap(long, value_name = "REGEX")]
+    grep: Option<String>,
+    /// Hide stdout and stderr of the command with this label
+    ///
+    /// Output is still read so that the command doesn't block,
+    /// and exits of the command are still shown.
+    #[clap(long, value_name = "LABEL")]
+    hide: Vec<String>,
+    /// Print failed commands with their last N lines of output when all commands exit
+    ///
+    /// Defaults to 10. 0 disables it.
+    #[clap(long, value_name = "N")]
+    tail_on_failure: Option<usize>,
+    /// Don't print the table of all commands with their statuses and durations at the end
+    #[clap(long)]
+    no_summary: bool,
+    /// Write a report file when all commands exit, like junit:runcc.xml or json:runcc.json
+    ///
+    /// In a JUnit report, each command is a test case,
+    /// with its last lines of output (see --tail-on-failure) if it failed.
+    /// Commands killed by runcc are marked as skipped.
+    #[clap(long, value_name = "FORMAT:PATH")]
+    report: Vec<ReportFile>,
+    /// Arguments to replace placeholders in commands
+    ///
+    /// {1}, {2}, ... : the n-th argument
+    ///
+    /// {@}           : all arguments
+    ///
+    /// {*}           : all arguments as one argument
+    ///
+    /// Arguments are quoted for the shell when replaced in command scripts.
+    #[clap(last = true)]
+    args: Vec<String>,
 }
 
 impl Opts {
@@ -51,18 +203,42 @@ impl Opts {
             command: commands,
             config,
             max_label_length,
+            label_truncate,
+            label_align,
+            label_style,
+            show_index,
             env,
+            env_file,
             kill,
+            color,
+            prefix,
+            timestamps,
+            timestamp_format,
+            log_dir,
+            output,
+            group_style,
+            show_progress,
+            partial_line_timeout,
+            grep,
+            hide,
+            tail_on_failure,
+            no_summary,
+            report,
+            args,
         } = self;
 
-        let envs = if env.len() > 0 {
+        let timestamps = timestamps.map(Option::unwrap_or_default);
+
+        let passthrough_args = if args.is_empty() { None } else { Some(args) };
+
+        let envs = if !env.is_empty() {
             let envs: HashMap<String, String> = env
                 .into_iter()
                 .map(|env| {
                     let (kv, program) = crate::env::match_one_env(&env);
 
                     if let Some(kv) = kv {
-                        if program == "" {
+                        if program.is_empty() {
                             Ok((kv.0.to_string(), kv.1.to_string()))
                         } else {
                             Err(env)
@@ -72,19 +248,21 @@ impl Opts {
                     }
                 })
                 .collect::<Result<_, _>>()
-                .or_else(|invalid_env| Err(OptionsError::EnvSyntaxError(invalid_env)))?;
+                .map_err(OptionsError::EnvSyntaxError)?;
 
             Some(envs)
         } else {
             None
         };
 
-        if commands.len() > 0 {
+        let mut config: RunConfig = if !commands.is_empty() {
             if config.is_some() {
                 return Err(OptionsError::DuplicateConfigs);
             }
 
-            Ok(RunConfigInput {
+            RunConfigInput {
+                extends: None,
+                include: None,
                 commands: CommandConfigsInput::Commands(
                     commands
                         .into_iter()
@@ -92,22 +270,75 @@ impl Opts {
                         .collect(),
                 ),
                 max_label_length,
-                kill: kill.unwrap_or_default(),
+                label_truncate,
+                label_align,
+                label_style,
+                show_index: if show_index { Some(true) } else { None },
+                kill,
                 envs,
-                windows_call_cmd_with_env: Default::default(),
+                env_file: if env_file.is_empty() {
+                    None
+                } else {
+                    Some(env_file)
+                },
+                env_clear: None,
+                env_remove: None,
+                env_inherit: None,
+                windows_call_cmd_with_env: None,
+                color,
+                child_env_defaults: None,
+                prefix,
+                timestamps,
+                timestamp_format,
+                log_dir,
+                log_file_options: None,
+                output,
+                group_style,
+                show_progress: if show_progress { Some(true) } else { None },
+                partial_line_timeout,
+                grep,
+                tail_on_failure,
+                show_summary: if no_summary { Some(false) } else { None },
+                reports: if report.is_empty() {
+                    None
+                } else {
+                    Some(report)
+                },
+                passthrough_args,
             }
-            .into())
+            .into()
         } else if let Some(config) = config {
-            let data: read::ConfigFileData<RunConfigInput> =
-                read::find_config_file(config.as_ref().and_then(|s| Some(s.as_str())), "runcc")
-                    .or_else(|err| Err(OptionsError::ConfigFileError(err)))?;
+            let data = read::find_layered_config_file(config.as_deref(), "runcc")
+                .map_err(OptionsError::ConfigFileError)?;
+
+            let mut input = data.data;
+            let colorize = color.or(input.color).unwrap_or_default().should_colorize();
 
-            eprintln!("[runcc][info] using config file {:?}", data.filename);
+            eprint_message(
+                colorize,
+                MessageLevel::Info,
+                format_args!("using config file {:?}", data.filename),
+            );
+            if data.layers.len() > 1 {
+                eprint_message(
+                    colorize,
+                    MessageLevel::Info,
+                    format_args!("merged config files {:?}", data.layers),
+                );
+            }
+
+            if passthrough_args.is_some() {
+                input.passthrough_args = passthrough_args;
+            }
 
-            let mut config: RunConfig = data.data.into();
+            let mut config: RunConfig = input.into();
 
             if let Some(envs) = envs {
-                eprintln!("[runcc][warning] env vars from cli args will be appended to envs from config file");
+                eprint_message(
+                    colorize,
+                    MessageLevel::Warning,
+                    "env vars from cli args will be appended to envs from config file",
+                );
                 if let Some(old_envs) = &mut config.envs {
                     old_envs.extend(envs);
                 } else {
@@ -115,24 +346,138 @@ impl Opts {
                 };
             }
 
+            if !env_file.is_empty() {
+                config
+                    .env_file
+                    .get_or_insert_with(Vec::new)
+                    .extend(env_file);
+            }
+
             if let Some(max_label_length) = max_label_length {
                 if max_label_length != config.max_label_length {
-                    eprintln!("[runcc][warning] max_label_length from cli args will override the value from config file");
+                    eprint_message(
+                        colorize,
+                        MessageLevel::Warning,
+                        "max_label_length from cli args will override the value from config file",
+                    );
                     config.max_label_length = max_label_length;
                 }
             }
 
+            if let Some(label_truncate) = label_truncate {
+                config.label_truncate = label_truncate;
+            }
+
+            if let Some(label_align) = label_align {
+                config.label_align = label_align;
+            }
+
+            if let Some(label_style) = label_style {
+                config.label_style = label_style;
+            }
+
+            if show_index {
+                config.show_index = true;
+            }
+
             if let Some(kill) = kill {
                 if kill != config.kill {
-                    eprintln!("[runcc][warning] kill from cli args will override the value from config file");
+                    eprint_message(
+                        colorize,
+                        MessageLevel::Warning,
+                        "kill from cli args will override the value from config file",
+                    );
                     config.kill = kill;
                 }
             }
 
-            Ok(config)
+            if let Some(color) = color {
+                config.color = color;
+            }
+
+            if prefix.is_some() {
+                config.prefix = prefix;
+            }
+
+            if timestamps.is_some() {
+                config.timestamps = timestamps;
+            }
+
+            if timestamp_format.is_some() {
+                config.timestamp_format = timestamp_format;
+            }
+
+            if log_dir.is_some() {
+                config.log_dir = log_dir;
+            }
+
+            if let Some(output) = output {
+                config.output = output;
+            }
+
+            if let Some(group_style) = group_style {
+                config.group_style = group_style;
+            }
+
+            if show_progress {
+                config.show_progress = true;
+            }
+
+            if partial_line_timeout.is_some() {
+                config.partial_line_timeout = partial_line_timeout;
+            }
+
+            if grep.is_some() {
+                config.grep = grep;
+            }
+
+            if tail_on_failure.is_some() {
+                config.tail_on_failure = tail_on_failure;
+            }
+
+            if no_summary {
+                config.show_summary = Some(false);
+            }
+
+            if !report.is_empty() {
+                config.reports = report;
+            }
+
+            config
         } else {
-            Err(OptionsError::NoConfigs)
+            return Err(OptionsError::NoConfigs);
+        };
+
+        config
+            .load_env_files()
+            .map_err(OptionsError::EnvFileError)?;
+
+        for label in hide {
+            let mut found = false;
+            for cmd in config.commands.iter_mut() {
+                if cmd.label_or_default() == label {
+                    cmd.stdout = Some(OutputVisibility::Hide);
+                    cmd.stderr = Some(OutputVisibility::Hide);
+                    found = true;
+                }
+            }
+
+            if !found {
+                eprint_message(
+                    config.color.should_colorize(),
+                    MessageLevel::Warning,
+                    format_args!("no command labeled {:?} to hide", label),
+                );
+            }
         }
+
+        if let Some(cmd) = config.command_without_log_file() {
+            return Err(OptionsError::MissingLogFile(
+                cmd.label_or_default().into_owned(),
+            ));
+        }
+
+        Ok(config)
     }
 }
 
@@ -146,4 +491,31 @@ mod tests {
         let opts = Opts::parse_from(["test", "--env", "A=a", "--env", "B=1"]);
         assert_eq!(opts.env, ["A=a", "B=1"]);
     }
+
+    #[test]
+    fn parse_passthrough_args() {
+        let opts = Opts::parse_from(["test", "cargo test {@}", "--", "-p", "--env"]);
+        assert_eq!(opts.command, ["cargo test {@}"]);
+        assert_eq!(opts.args, ["-p", "--env"]);
+    }
+
+    #[test]
+    fn parse_timestamps() {
+        let opts = Opts::parse_from(["test", "--timestamps", "echo"]);
+        assert_eq!(opts.timestamps, Some(None));
+        assert_eq!(opts.command, ["echo"]);
+
+        let opts = Opts::parse_from(["test", "--timestamps=since_start", "echo"]);
+        assert_eq!(
+            opts.timestamps,
+            Some(Some(crate::TimestampMode::SinceStart))
+        );
+    }
+
+    #[test]
+    fn parse_color() {
+        let opts = Opts::parse_from(["test", "--color", "never", "echo"]);
+        assert_eq!(opts.color, Some(crate::ColorChoice::Never));
+        assert!(Opts::try_parse_from(["test", "--color", "maybe", "echo"]).is_err());
+    }
 }
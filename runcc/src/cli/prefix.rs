@@ -0,0 +1,254 @@
+use std::{fmt::Display, time::Duration};
+
+use crate::LabelStyle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PrefixField {
+    Time,
+    Label,
+    Index,
+    Pid,
+    Stream,
+    Elapsed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PrefixSegment {
+    Text(String),
+    Field(PrefixField),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PrefixTemplateError {
+    UnknownField(String),
+    UnclosedBrace(String),
+}
+
+impl std::error::Error for PrefixTemplateError {}
+
+impl Display for PrefixTemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrefixTemplateError::UnknownField(field) => write!(
+                f,
+                "unknown field {{{}}} in prefix, expected one of {{time}}, {{label}}, {{index}}, {{pid}}, {{stream}}, {{elapsed}}",
+                field
+            ),
+            PrefixTemplateError::UnclosedBrace(template) => {
+                write!(f, "unclosed \"{{\" in prefix \"{}\"", template)
+            }
+        }
+    }
+}
+
+/// Values of fields to render a prefix for a line
+pub struct PrefixData<'a> {
+    /// Formatted time when the line is read
+    pub time: &'a str,
+    pub label: &'a str,
+    pub index: usize,
+    pub pid: Option<u32>,
+    /// `out` or `err`
+    pub stream: &'a str,
+    /// Time elapsed since the command was spawned
+    pub elapsed: Duration,
+}
+
+/// Template of the prefix of each output line, like `[{label}]`.
+///
+/// `{{` and `}}` are literal braces.
+/// `none` means no prefix, so output is passed through as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefixTemplate(Option<Vec<PrefixSegment>>);
+
+impl Default for PrefixTemplate {
+    fn default() -> Self {
+        Self::parse(Self::DEFAULT).unwrap()
+    }
+}
+
+impl PrefixTemplate {
+    pub const DEFAULT: &'static str = "[{label}]";
+    pub const NONE: &'static str = "none";
+
+    /// Default template for labels in `style`, like `[{label}]` or `{label} |`
+    pub fn from_label_style(style: LabelStyle) -> Self {
+        Self::parse(&style.wrap("{label}")).unwrap()
+    }
+
+    pub fn parse(template: &str) -> Result<Self, PrefixTemplateError> {
+        if template == Self::NONE {
+            return Ok(Self(None));
+        }
+
+        let mut segments = vec![];
+        let mut text = String::new();
+        let mut chars = template.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|(_, c)| *c == '{').is_some() => text.push('{'),
+                '}' if chars.next_if(|(_, c)| *c == '}').is_some() => text.push('}'),
+                '{' => {
+                    let rest = &template[(i + 1)..];
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| PrefixTemplateError::UnclosedBrace(template.to_string()))?;
+                    let name = &rest[..end];
+
+                    let field = match name {
+                        "time" => PrefixField::Time,
+                        "label" => PrefixField::Label,
+                        "index" => PrefixField::Index,
+                        "pid" => PrefixField::Pid,
+                        "stream" => PrefixField::Stream,
+                        "elapsed" => PrefixField::Elapsed,
+                        _ => return Err(PrefixTemplateError::UnknownField(name.to_string())),
+                    };
+
+                    if !text.is_empty() {
+                        segments.push(PrefixSegment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(PrefixSegment::Field(field));
+
+                    while chars.next_if(|(j, _)| *j <= i + 1 + end).is_some() {}
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            segments.push(PrefixSegment::Text(text));
+        }
+
+        Ok(Self(Some(segments)))
+    }
+
+    /// Whether output should be passed through without prefix
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Whether `{time}` is in the template
+    pub fn has_time(&self) -> bool {
+        self.0
+            .iter()
+            .flatten()
+            .any(|segment| *segment == PrefixSegment::Field(PrefixField::Time))
+    }
+
+    /// Prepend `{time}` to the template if it's not in the template
+    pub fn with_time(self) -> Self {
+        if self.has_time() {
+            return self;
+        }
+
+        let mut segments = vec![PrefixSegment::Field(PrefixField::Time)];
+        let mut rest = self.0.unwrap_or_default().into_iter();
+        match rest.next() {
+            Some(PrefixSegment::Text(text)) => {
+                segments.push(PrefixSegment::Text(format!(" {}", text)))
+            }
+            Some(field) => {
+                segments.push(PrefixSegment::Text(" ".to_string()));
+                segments.push(field);
+            }
+            None => {}
+        }
+        segments.extend(rest);
+
+        Self(Some(segments))
+    }
+
+    /// Render the prefix, without the space separating it from the line
+    pub fn render(&self, data: &PrefixData) -> String {
+        let segments = match &self.0 {
+            Some(segments) => segments,
+            None => return String::new(),
+        };
+
+        let mut res = String::new();
+        for segment in segments {
+            match segment {
+                PrefixSegment::Text(text) => res.push_str(text),
+                PrefixSegment::Field(field) => match field {
+                    PrefixField::Time => res.push_str(data.time),
+                    PrefixField::Label => res.push_str(data.label),
+                    PrefixField::Index => res.push_str(&data.index.to_string()),
+                    PrefixField::Pid => {
+                        if let Some(pid) = data.pid {
+                            res.push_str(&pid.to_string())
+                        }
+                    }
+                    PrefixField::Stream => res.push_str(data.stream),
+                    PrefixField::Elapsed => {
+                        res.push_str(&format!("{:.3}s", data.elapsed.as_secs_f64()))
+                    }
+                },
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrefixData, PrefixTemplate, PrefixTemplateError};
+    use std::time::Duration;
+
+    fn render(template: &str) -> String {
+        PrefixTemplate::parse(template)
+            .unwrap()
+            .render(&PrefixData {
+                time: "12:00:00",
+                label: "api",
+                index: 1,
+                pid: Some(42),
+                stream: "err",
+                elapsed: Duration::from_millis(1500),
+            })
+    }
+
+    #[test]
+    fn render_prefix() {
+        for (template, res) in [
+            (PrefixTemplate::DEFAULT, "[api]"),
+            ("{index}:{label} {stream} |", "1:api err |"),
+            ("{pid} +{elapsed}", "42 +1.500s"),
+            ("{{label}} 你好", "{label} 你好"),
+            ("{time} {label}", "12:00:00 api"),
+            ("", ""),
+        ] {
+            assert_eq!(render(template), res, "{:?}", template);
+        }
+
+        assert!(PrefixTemplate::parse("none").unwrap().is_none());
+    }
+
+    #[test]
+    fn prefix_with_time() {
+        for (template, res) in [
+            ("[{label}]", "{time} [{label}]"),
+            ("{label} {time}", "{label} {time}"),
+            ("none", "{time}"),
+        ] {
+            assert_eq!(
+                PrefixTemplate::parse(template).unwrap().with_time(),
+                PrefixTemplate::parse(res).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn parse_prefix_fail() {
+        assert_eq!(
+            PrefixTemplate::parse("{date}"),
+            Err(PrefixTemplateError::UnknownField("date".to_string()))
+        );
+        assert_eq!(
+            PrefixTemplate::parse("[{label"),
+            Err(PrefixTemplateError::UnclosedBrace("[{label".to_string()))
+        );
+    }
+}
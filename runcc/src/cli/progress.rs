@@ -0,0 +1,128 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::log::OutputStream;
+use super::log_file::strip_ansi_escapes;
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    /// Index of the command and the progress line shown on the terminal
+    shown: Option<(usize, String)>,
+    last_drawn: Option<Instant>,
+}
+
+/// Shows the line being written by a command, like a progress bar,
+/// in place at the bottom of the terminal while other lines are printed above it.
+///
+/// If disabled, lines are printed as is.
+#[derive(Debug)]
+pub(super) struct ProgressLine {
+    enabled: bool,
+    state: Mutex<ProgressState>,
+}
+
+impl ProgressLine {
+    /// Min interval to redraw the progress line
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    pub(super) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            state: Default::default(),
+        }
+    }
+
+    /// Print a line of command `index` above the progress line.
+    ///
+    /// The progress of the same command is removed because the line is ended.
+    pub(super) fn print_line(&self, index: usize, stream: OutputStream, line: &str) {
+        if !self.enabled {
+            stream.write_line(line);
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let mut stdout = io::stdout().lock();
+
+        if let Some((shown_index, _)) = &state.shown {
+            let _ = write!(stdout, "\r\x1B[2K");
+            if *shown_index == index {
+                state.shown = None;
+            }
+        }
+
+        let _ = match stream {
+            OutputStream::Stdout => writeln!(stdout, "{}", line),
+            OutputStream::Stderr => {
+                let _ = stdout.flush();
+                writeln!(io::stderr(), "{}", line)
+            }
+        };
+
+        if let Some((_, progress)) = &state.shown {
+            let _ = write!(stdout, "{}", progress);
+        }
+        let _ = stdout.flush();
+    }
+
+    /// Show the line being written by command `index`, throttled by [`Self::INTERVAL`]
+    pub(super) fn update(&self, index: usize, progress: &str) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let progress = fit_terminal_width(progress);
+
+        let now = Instant::now();
+        let throttled = state
+            .last_drawn
+            .is_some_and(|last_drawn| now.duration_since(last_drawn) < Self::INTERVAL);
+
+        if !throttled {
+            let mut stdout = io::stdout().lock();
+            let _ = write!(stdout, "\r\x1B[2K{}", progress);
+            let _ = stdout.flush();
+            state.last_drawn = Some(now);
+        }
+
+        state.shown = Some((index, progress));
+    }
+
+    /// Remove the progress of command `index` when its output ends
+    pub(super) fn clear(&self, index: usize) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if matches!(&state.shown, Some((shown_index, _)) if *shown_index == index) {
+            let mut stdout = io::stdout().lock();
+            let _ = write!(stdout, "\r\x1B[2K");
+            let _ = stdout.flush();
+            state.shown = None;
+        }
+    }
+}
+
+/// Remove styles and truncate `line` so that it doesn't wrap,
+/// otherwise it can't be erased in place.
+fn fit_terminal_width(line: &str) -> String {
+    let width = terminal_width().saturating_sub(1);
+    crate::unicode::take_width(&strip_ansi_escapes(line), width).to_string()
+}
+
+/// Columns of the terminal of stdout, or `COLUMNS` env var, or 80
+fn terminal_width() -> usize {
+    if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size() {
+        if width > 0 {
+            return width as usize;
+        }
+    }
+
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(80)
+}
@@ -0,0 +1,270 @@
+use std::io;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::decode::Decoder;
+use super::log::drain;
+use crate::terminal::VirtualLine;
+use crate::Encoding;
+
+/// A final rendered line of output
+pub(super) struct OutputLine {
+    pub text: String,
+    /// The line is not ended, but flushed because the command has been idle or exited
+    pub partial: bool,
+    /// The line continues a partial line flushed before
+    pub continued: bool,
+}
+
+/// What is read from output of a command
+pub(super) enum OutputEvent {
+    Line(OutputLine),
+    /// The line being written, which is not ended yet, like a progress bar
+    Progress(String),
+}
+
+/// Read output of a command as chunks, decode them lossily in `encoding`
+/// and render lines with [`VirtualLine`].
+///
+/// If `partial_line_timeout` is `Some`, a line which is not ended,
+/// like a prompt, is flushed after no output is read for this duration.
+/// A line redrawn with `\r` like a spinner is never idle,
+/// so the frame before `\r` is flushed if the line has not been flushed for this duration.
+///
+/// Output is read until the end, so that the command never blocks on a full pipe.
+/// If reading fails, the line being written is flushed,
+/// the rest of output is drained and the error is returned.
+pub(super) async fn read_output<R: AsyncRead + Unpin>(
+    mut reader: R,
+    encoding: Encoding,
+    partial_line_timeout: Option<Duration>,
+    mut on_event: impl FnMut(OutputEvent),
+) -> io::Result<()> {
+    let mut decoder = Decoder::new(encoding);
+    let mut line = VirtualLine::default();
+    let mut lines = vec![];
+    let mut continued = false;
+    // when the line being written was started or flushed
+    let mut pending_since = None;
+    let mut buf = vec![0u8; 8 * 1024];
+    let mut text = String::new();
+
+    let res = loop {
+        let res = match partial_line_timeout {
+            Some(timeout) if !line.is_empty() => {
+                match tokio::time::timeout(timeout, reader.read(&mut buf)).await {
+                    Ok(res) => res,
+                    Err(_) => {
+                        if let Some(text) = line.take_partial() {
+                            on_event(OutputEvent::Line(OutputLine {
+                                text,
+                                partial: true,
+                                continued,
+                            }));
+                            continued = true;
+                        }
+                        continue;
+                    }
+                }
+            }
+            _ => reader.read(&mut buf).await,
+        };
+
+        let n = match res {
+            Ok(0) => break Ok(()),
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => break Err(err),
+        };
+
+        decoder.decode(&buf[..n], &mut text);
+        line.feed(&text, &mut lines);
+        text.clear();
+
+        for text in lines.drain(..) {
+            on_event(OutputEvent::Line(OutputLine {
+                text,
+                partial: false,
+                continued,
+            }));
+            continued = false;
+            pending_since = None;
+        }
+
+        if line.is_empty() {
+            pending_since = None;
+            continue;
+        }
+
+        let since = *pending_since.get_or_insert_with(Instant::now);
+        if partial_line_timeout.is_some_and(|timeout| since.elapsed() >= timeout) {
+            if let Some(text) = line.take_overwritten() {
+                on_event(OutputEvent::Line(OutputLine {
+                    text,
+                    partial: true,
+                    continued,
+                }));
+                continued = true;
+                pending_since = Some(Instant::now());
+            }
+        }
+
+        on_event(OutputEvent::Progress(line.render()));
+    };
+
+    decoder.finish(&mut text);
+    line.feed(&text, &mut lines);
+    for text in lines.drain(..) {
+        on_event(OutputEvent::Line(OutputLine {
+            text,
+            partial: false,
+            continued,
+        }));
+        continued = false;
+    }
+
+    if let Some(text) = line.finish() {
+        on_event(OutputEvent::Line(OutputLine {
+            text,
+            partial: true,
+            continued,
+        }));
+    }
+
+    if res.is_err() {
+        drain(reader).await;
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_output, OutputEvent};
+    use crate::Encoding;
+    use std::collections::VecDeque;
+    use std::io;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
+
+    /// Reads chunks or errors in order
+    struct ChunksReader(Arc<Mutex<VecDeque<io::Result<&'static [u8]>>>>);
+
+    impl AsyncRead for ChunksReader {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Poll::Ready(match self.0.lock().unwrap().pop_front() {
+                Some(Ok(chunk)) => {
+                    buf.put_slice(chunk);
+                    Ok(())
+                }
+                Some(Err(err)) => Err(err),
+                None => Ok(()),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn flush_and_drain_after_read_error() {
+        let chunks = Arc::new(Mutex::new(VecDeque::from([
+            Ok(&b"done\nhalf"[..]),
+            Err(io::Error::other("broken")),
+            Ok(&b"rest\n"[..]),
+        ])));
+        let reader = ChunksReader(chunks.clone());
+
+        let mut lines = vec![];
+        let res = read_output(reader, Encoding::Utf8, None, |event| {
+            if let OutputEvent::Line(line) = event {
+                lines.push((line.text, line.partial));
+            }
+        })
+        .await;
+
+        assert_eq!(res.unwrap_err().to_string(), "broken");
+        assert_eq!(
+            lines,
+            vec![("done".to_string(), false), ("half".to_string(), true)]
+        );
+        // the rest is drained
+        assert!(chunks.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn flush_partial_line_when_idle() {
+        let (mut writer, reader) = tokio::io::duplex(64);
+
+        let write = async move {
+            writer.write_all(b"Password: ").await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            writer.write_all(b"ok\n10%\r20%").await.unwrap();
+        };
+
+        let mut lines = vec![];
+        let read = read_output(
+            reader,
+            Encoding::Utf8,
+            Some(Duration::from_millis(20)),
+            |event| {
+                if let OutputEvent::Line(line) = event {
+                    lines.push((line.text, line.partial, line.continued));
+                }
+            },
+        );
+
+        let (res, ()) = tokio::join!(read, write);
+        res.unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                ("Password: ".to_string(), true, false),
+                ("ok".to_string(), false, true),
+                ("20%".to_string(), true, false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn flush_spinner_without_newline() {
+        let (mut writer, reader) = tokio::io::duplex(64);
+
+        let write = async move {
+            for frame in ["|", "/", "-", "\\"].iter().cycle().take(12) {
+                writer.write_all(frame.as_bytes()).await.unwrap();
+                writer.write_all(b" building\r").await.unwrap();
+                tokio::time::sleep(Duration::from_millis(15)).await;
+            }
+        };
+
+        let mut lines = vec![];
+        let read = read_output(
+            reader,
+            Encoding::Utf8,
+            Some(Duration::from_millis(40)),
+            |event| {
+                if let OutputEvent::Line(line) = event {
+                    lines.push((line.text, line.partial, line.continued));
+                }
+            },
+        );
+
+        let (res, ()) = tokio::join!(read, write);
+        res.unwrap();
+
+        // flushed while the spinner is running, and at last when it exits
+        assert!(lines.len() >= 3, "{:?}", lines);
+        assert!(lines.len() < 12, "{:?}", lines);
+        for (i, (text, partial, continued)) in lines.iter().enumerate() {
+            assert!(text.ends_with(" building"), "{:?}", text);
+            assert!(partial);
+            assert_eq!(*continued, i > 0);
+        }
+    }
+}
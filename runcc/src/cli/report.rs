@@ -0,0 +1,283 @@
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::log_file::strip_ansi_escapes;
+use super::tail::OutputTails;
+use super::timestamp::format_rfc3339_utc;
+use super::{eprint_message, MessageLevel};
+use crate::run::{CommandReport, CommandSystemReport, KillReport};
+use crate::{ReportFile, ReportFormat};
+
+/// Write `reports` and print errors instead of failing, so that one bad path doesn't hide others
+pub(super) fn write_reports(
+    reports: &[ReportFile],
+    report: &CommandSystemReport,
+    tails: &OutputTails,
+    colorize: bool,
+) {
+    for file in reports {
+        let content = match file.format {
+            ReportFormat::Junit => Ok(render_junit(report, |index| tails.lines(index))),
+            ReportFormat::Json => serde_json::to_string_pretty(report)
+                .map(|json| json + "\n")
+                .map_err(io::Error::from),
+        };
+
+        if let Err(err) = content.and_then(|content| write_file(&file.path, &content)) {
+            eprint_message(
+                colorize,
+                MessageLevel::Error,
+                format_args!("failed to write report to {}: {}", file.path.display(), err),
+            );
+        }
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+    }
+
+    fs::write(path, content)
+}
+
+/// How a command is shown as a JUnit test case
+enum TestCaseResult {
+    Passed,
+    /// Exited unsuccessfully, with a message and the last lines of output
+    Failure(String),
+    /// Failed to spawn or to be waited
+    Error(String),
+    /// Killed by runcc, usually because another command exited
+    Skipped(String),
+}
+
+impl TestCaseResult {
+    fn new(cmd: &CommandReport) -> Self {
+        if let Some(err) = &cmd.spawn_error {
+            return Self::Error(format!("failed to spawn: {}", err));
+        }
+        if let Some(err) = &cmd.wait_error {
+            return Self::Error(format!("failed to wait: {}", err));
+        }
+        if cmd.success() {
+            return Self::Passed;
+        }
+
+        let status = cmd
+            .exit_status
+            .map_or_else(|| "unknown".to_string(), |status| status.to_string());
+
+        match &cmd.kill {
+            Some(KillReport::Killed { reason }) => {
+                Self::Skipped(format!("killed due to {}", reason))
+            }
+            Some(KillReport::FailedToKill { reason, error }) => Self::Failure(format!(
+                "exited with {} (failed to kill due to {}: {})",
+                status, reason, error
+            )),
+            _ => Self::Failure(format!("exited with {}", status)),
+        }
+    }
+}
+
+/// Escape text for XML attributes and text,
+/// dropping control characters which are not allowed in XML 1.0
+fn escape_xml(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            '\t' | '\n' | '\r' => res.push(c),
+            c if c.is_control() => {}
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Render `report` as JUnit XML, where each command is a test case.
+///
+/// `tail` returns the last lines of output of the `index`-th command,
+/// which are written in failures.
+fn render_junit(report: &CommandSystemReport, tail: impl Fn(usize) -> Vec<String>) -> String {
+    let results: Vec<_> = report.commands.iter().map(TestCaseResult::new).collect();
+    let count = |f: fn(&TestCaseResult) -> bool| results.iter().filter(|r| f(r)).count();
+    let failures = count(|r| matches!(r, TestCaseResult::Failure(_)));
+    let errors = count(|r| matches!(r, TestCaseResult::Error(_)));
+    let skipped = count(|r| matches!(r, TestCaseResult::Skipped(_)));
+
+    let started = report
+        .commands
+        .iter()
+        .filter_map(|cmd| cmd.spawned_at)
+        .min();
+    let exited = report.commands.iter().map(|cmd| cmd.exited_at).max();
+    let time = match (started, exited) {
+        (Some(started), Some(exited)) => exited
+            .duration_since(started)
+            .unwrap_or_default()
+            .as_secs_f64(),
+        _ => 0.0,
+    };
+
+    let attrs = format!(
+        r#"name="runcc" tests="{}" failures="{}" errors="{}" skipped="{}" time="{:.3}""#,
+        results.len(),
+        failures,
+        errors,
+        skipped,
+        time
+    );
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, "<testsuites {}>", attrs);
+    let _ = match started {
+        Some(started) => writeln!(
+            xml,
+            r#"  <testsuite {} timestamp="{}">"#,
+            attrs,
+            format_rfc3339_utc(started)
+        ),
+        None => writeln!(xml, "  <testsuite {}>", attrs),
+    };
+
+    for (cmd, result) in report.commands.iter().zip(&results) {
+        let _ = write!(
+            xml,
+            r#"    <testcase name="{}" classname="runcc" time="{:.3}""#,
+            escape_xml(&cmd.label),
+            cmd.duration.as_secs_f64()
+        );
+
+        let _ = match result {
+            TestCaseResult::Passed => writeln!(xml, "/>"),
+            TestCaseResult::Error(message) => writeln!(
+                xml,
+                ">\n      <error message=\"{}\"/>\n    </testcase>",
+                escape_xml(message)
+            ),
+            TestCaseResult::Skipped(message) => writeln!(
+                xml,
+                ">\n      <skipped message=\"{}\"/>\n    </testcase>",
+                escape_xml(message)
+            ),
+            TestCaseResult::Failure(message) => {
+                let output: Vec<_> = tail(cmd.index)
+                    .iter()
+                    .map(|line| escape_xml(&strip_ansi_escapes(line)))
+                    .collect();
+                writeln!(
+                    xml,
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                    escape_xml(message),
+                    output.join("\n")
+                )
+            }
+        };
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{escape_xml, render_junit};
+    use crate::run::{CommandReport, CommandSystemReport, ExitStatusReport, KillReport};
+
+    fn command(index: usize, label: &str, code: Option<i32>) -> CommandReport {
+        let spawned_at = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        CommandReport {
+            index,
+            label: label.to_string(),
+            pid: Some(100 + index as u32),
+            argv: vec!["sh".to_string()],
+            spawn_error: None,
+            wait_error: None,
+            exit_status: Some(ExitStatusReport {
+                success: code == Some(0),
+                code,
+                signal: if code.is_none() { Some(15) } else { None },
+                signal_name: if code.is_none() {
+                    Some("SIGTERM")
+                } else {
+                    None
+                },
+                core_dumped: false,
+            }),
+            kill: None,
+            spawned_at: Some(spawned_at),
+            exited_at: spawned_at + Duration::from_millis(1500),
+            duration: Duration::from_millis(1500),
+            peak_memory: None,
+        }
+    }
+
+    #[test]
+    fn render_test_cases() {
+        let mut killed = command(2, "server", None);
+        killed.kill = Some(KillReport::Killed {
+            reason: "command[test] exited".to_string(),
+        });
+        let mut spawn_failed = command(3, "lint", None);
+        spawn_failed.exit_status = None;
+        spawn_failed.spawned_at = None;
+        spawn_failed.spawn_error = Some("not found".to_string());
+
+        let report = CommandSystemReport {
+            commands: vec![
+                command(0, "build", Some(0)),
+                command(1, "test", Some(1)),
+                killed,
+                spawn_failed,
+            ],
+        };
+
+        let xml = render_junit(&report, |index| {
+            assert_eq!(index, 1);
+            vec!["\x1B[31massert a < b\x1B[0m".to_string()]
+        });
+
+        assert_eq!(
+            xml,
+            [
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<testsuites name="runcc" tests="4" failures="1" errors="1" skipped="1" time="1.500">"#,
+                r#"  <testsuite name="runcc" tests="4" failures="1" errors="1" skipped="1" time="1.500" timestamp="2020-09-13T12:26:40.000Z">"#,
+                r#"    <testcase name="build" classname="runcc" time="1.500"/>"#,
+                r#"    <testcase name="test" classname="runcc" time="1.500">"#,
+                r#"      <failure message="exited with code 1">assert a &lt; b</failure>"#,
+                r#"    </testcase>"#,
+                r#"    <testcase name="server" classname="runcc" time="1.500">"#,
+                r#"      <skipped message="killed due to command[test] exited"/>"#,
+                r#"    </testcase>"#,
+                r#"    <testcase name="lint" classname="runcc" time="1.500">"#,
+                r#"      <error message="failed to spawn: not found"/>"#,
+                r#"    </testcase>"#,
+                r#"  </testsuite>"#,
+                r#"</testsuites>"#,
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            escape_xml("<a href=\"x\">&'\x07\t"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&apos;\t"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncRead;
+
+use super::filter::OutputFilter;
+use super::log::{drain, OutputStream};
+use super::log_file::LogFileWriter;
+use super::reader::{read_output, OutputEvent};
+use super::tail::{OutputTails, TailBuffer};
+use super::{eprint_message, LogOptions, MessageLevel};
+use crate::run::LabeledCommandData;
+use crate::{Encoding, OutputVisibility};
+
+/// Where output lines of a command go before they are printed.
+///
+/// Every line is written to the log file and the tail buffer,
+/// and then passed to the plugin if it is shown by `stdout` / `stderr` and output rules.
+pub(super) struct OutputSink {
+    label: String,
+    colorize: bool,
+    encoding: Encoding,
+    partial_line_timeout: Option<Duration>,
+    pub(super) filter: OutputFilter,
+    /// Set to `None` after failing to write
+    log_file: Mutex<Option<LogFileWriter>>,
+    tail: Arc<Mutex<TailBuffer>>,
+}
+
+impl OutputSink {
+    pub(super) fn new(
+        data: &LabeledCommandData,
+        options: &LogOptions,
+        tails: &OutputTails,
+    ) -> Self {
+        Self {
+            label: data.label.label().to_string(),
+            colorize: options.colorize,
+            encoding: data.encoding,
+            partial_line_timeout: options.partial_line_timeout,
+            filter: options
+                .output_filters
+                .get(data.index)
+                .cloned()
+                .unwrap_or_default(),
+            log_file: Mutex::new(open_log_file(data, options)),
+            tail: tails.insert(data.index, options.tail_on_failure),
+        }
+    }
+
+    /// Read output of a stream until it ends, calling `on_event` with shown lines,
+    /// and with the line being written if the stream is shown in the terminal.
+    pub(super) async fn read<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        stream: OutputStream,
+        visibility: OutputVisibility,
+        mut on_event: impl FnMut(OutputEvent),
+    ) {
+        if visibility == OutputVisibility::Hide {
+            return drain(reader).await;
+        }
+
+        let res = read_output(
+            reader,
+            self.encoding,
+            self.partial_line_timeout,
+            |event| match event {
+                OutputEvent::Line(line) => {
+                    self.write_log_file(&line.text);
+                    self.tail.lock().unwrap().push(&line.text);
+                    if visibility == OutputVisibility::Show && self.filter.is_shown(&line.text) {
+                        on_event(OutputEvent::Line(line));
+                    }
+                }
+                OutputEvent::Progress(progress) => {
+                    if visibility == OutputVisibility::Show && self.filter.is_shown(&progress) {
+                        on_event(OutputEvent::Progress(progress));
+                    }
+                }
+            },
+        )
+        .await;
+
+        if let Err(err) = res {
+            eprint_message(
+                self.colorize,
+                MessageLevel::Error,
+                format_args!(
+                    "failed to read line from [{}] std{}: {}",
+                    self.label,
+                    stream.name(),
+                    err
+                ),
+            );
+        }
+    }
+
+    /// Write a line to the log file.
+    ///
+    /// If it fails, an error is printed and the log file is set to `None`.
+    fn write_log_file(&self, line: &str) {
+        let mut log_file = self.log_file.lock().unwrap();
+
+        if let Some(writer) = &mut *log_file {
+            if let Err(err) = writer.write_line(line) {
+                eprint_message(
+                    self.colorize,
+                    MessageLevel::Error,
+                    format_args!(
+                        "failed to write log file {:?} of [{}], stop writing: {}",
+                        writer.path(),
+                        self.label,
+                        err
+                    ),
+                );
+                *log_file = None;
+            }
+        }
+    }
+}
+
+/// Open the log file of a command, printing an error if it fails
+fn open_log_file(data: &LabeledCommandData, options: &LogOptions) -> Option<LogFileWriter> {
+    let path = data.log_file.as_ref()?;
+
+    LogFileWriter::open(path.clone(), options.log_file_options.clone())
+        .map_err(|err| {
+            eprint_message(
+                options.colorize,
+                MessageLevel::Error,
+                format_args!(
+                    "failed to open log file {:?} of [{}]: {}",
+                    path,
+                    data.label.label(),
+                    err
+                ),
+            )
+        })
+        .ok()
+}
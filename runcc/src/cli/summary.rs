@@ -0,0 +1,170 @@
+use std::io;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::timestamp::{LineTime, TimestampFormat};
+use crate::run::ExitStatusReport;
+use crate::unicode::str_width;
+use crate::TimestampMode;
+
+/// A row of the summary table, for one command
+#[derive(Debug)]
+pub(super) struct SummaryRow {
+    pub(super) index: usize,
+    pub(super) label: String,
+    /// Like `code 0`, `signal 9`, `failed to spawn` or `failed to wait`
+    pub(super) status: String,
+    /// Why runcc killed the command
+    pub(super) killed: Option<String>,
+    /// Formatted start time, `None` if it failed to spawn
+    pub(super) started: Option<String>,
+    /// Formatted end time
+    pub(super) ended: String,
+    pub(super) duration: Duration,
+    /// Peak memory of the spawned process in bytes, not including its children
+    pub(super) peak_memory: Option<u64>,
+}
+
+/// Like `code 0`, `signal 9`, `failed to spawn` or `failed to wait`,
+/// where `spawned` tells whether an error is from waiting a spawned process
+pub(super) fn describe_status(status: &io::Result<ExitStatus>, spawned: bool) -> String {
+    match status {
+        Ok(status) => ExitStatusReport::from(*status).to_string(),
+        Err(_) if spawned => "failed to wait".to_string(),
+        Err(_) => "failed to spawn".to_string(),
+    }
+}
+
+/// Local time like `12:34:56`
+pub(super) fn format_summary_time(time: SystemTime) -> String {
+    let format = TimestampFormat::parse(TimestampMode::Wall, "%T").unwrap();
+    // instant is only used for time since start
+    let now = Instant::now();
+    format.format(
+        &LineTime {
+            instant: now,
+            system: time,
+        },
+        now,
+    )
+}
+
+/// Like `12.3 MiB`
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Render rows as a table with aligned columns, sorted by command index
+pub(super) fn render_summary_table(rows: &mut [SummaryRow]) -> String {
+    rows.sort_by_key(|row| row.index);
+
+    let header = [
+        "command", "status", "killed", "start", "end", "duration", "memory",
+    ];
+    let cells: Vec<[String; 7]> = rows
+        .iter()
+        .map(|row| {
+            [
+                row.label.clone(),
+                row.status.clone(),
+                row.killed.clone().unwrap_or_else(|| "-".to_string()),
+                row.started.clone().unwrap_or_else(|| "-".to_string()),
+                row.ended.clone(),
+                format!("{:.3}s", row.duration.as_secs_f64()),
+                row.peak_memory
+                    .map_or_else(|| "-".to_string(), format_bytes),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str_width);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(str_width(cell));
+        }
+    }
+
+    let render_row = |row: &mut dyn Iterator<Item = &str>| {
+        let line = row
+            .zip(widths)
+            .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - str_width(cell))))
+            .collect::<Vec<_>>()
+            .join("  ");
+        line.trim_end().to_string()
+    };
+
+    let mut lines = vec![render_row(&mut header.iter().copied())];
+    for row in &cells {
+        lines.push(render_row(&mut row.iter().map(String::as_str)));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{describe_status, format_bytes, render_summary_table, SummaryRow};
+
+    #[test]
+    fn render_table() {
+        let mut rows = vec![
+            SummaryRow {
+                index: 1,
+                label: "web".to_string(),
+                status: "code 1".to_string(),
+                killed: None,
+                started: Some("12:00:00".to_string()),
+                ended: "12:00:02".to_string(),
+                duration: Duration::from_millis(1500),
+                peak_memory: None,
+            },
+            SummaryRow {
+                index: 0,
+                label: "服务".to_string(),
+                status: "signal 15".to_string(),
+                killed: Some("Ctrl-C signal".to_string()),
+                started: Some("12:00:00".to_string()),
+                ended: "12:00:03".to_string(),
+                duration: Duration::from_secs(3),
+                peak_memory: Some(12 * 1024 * 1024),
+            },
+        ];
+
+        assert_eq!(
+            render_summary_table(&mut rows),
+            [
+                "command  status     killed         start     end       duration  memory",
+                "服务     signal 15  Ctrl-C signal  12:00:00  12:00:03  3.000s    12.0 MiB",
+                "web      code 1     -              12:00:00  12:00:02  1.500s    -",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn describe_errors() {
+        let err = || Err(std::io::Error::other("error"));
+        assert_eq!(describe_status(&err(), false), "failed to spawn");
+        assert_eq!(describe_status(&err(), true), "failed to wait");
+    }
+
+    #[test]
+    fn format_memory() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use super::log::OutputStream;
+use super::{eprint_message, MessageLevel};
+
+/// The last lines of output of a command, at most `capacity` lines
+#[derive(Debug)]
+pub(super) struct TailBuffer {
+    capacity: usize,
+    lines: VecDeque<String>,
+}
+
+impl TailBuffer {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Push a line, dropping the oldest one if the buffer is full
+    pub(super) fn push(&mut self, line: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.to_string());
+    }
+
+    pub(super) fn lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+}
+
+/// Tail buffers of commands by command index, shared with report writers
+#[derive(Debug, Default, Clone)]
+pub(super) struct OutputTails(Arc<Mutex<HashMap<usize, Arc<Mutex<TailBuffer>>>>>);
+
+impl OutputTails {
+    /// Create the tail buffer of the `index`-th command
+    pub(super) fn insert(&self, index: usize, capacity: usize) -> Arc<Mutex<TailBuffer>> {
+        let tail = Arc::new(Mutex::new(TailBuffer::new(capacity)));
+        self.0.lock().unwrap().insert(index, tail.clone());
+        tail
+    }
+
+    /// `None` if the command failed to spawn
+    pub(super) fn get(&self, index: usize) -> Option<Arc<Mutex<TailBuffer>>> {
+        self.0.lock().unwrap().get(&index).cloned()
+    }
+
+    /// Last lines of the `index`-th command
+    pub(super) fn lines(&self, index: usize) -> Vec<String> {
+        self.get(index).map_or_else(Vec::new, |tail| {
+            tail.lock().unwrap().lines().map(str::to_string).collect()
+        })
+    }
+}
+
+/// A command which didn't exit successfully, to be listed at shutdown
+#[derive(Debug)]
+pub(super) struct FailedCommand {
+    /// Formatted label, like `[api]`
+    pub(super) label: String,
+    /// Like `exited with status code 1 (killed due to Ctrl-C signal)`
+    pub(super) status: String,
+    /// Whether it's killed by runcc, like due to Ctrl-C or `kill`, rather than failed by itself
+    pub(super) killed: bool,
+    /// `None` if the command failed to spawn
+    pub(super) tail: Option<Arc<Mutex<TailBuffer>>>,
+}
+
+/// Failed commands to be printed when all commands exit
+#[derive(Debug, Default)]
+pub(super) struct FailureSummary {
+    /// Count of exited commands, including the failed ones
+    pub(super) exited: usize,
+    pub(super) failures: Vec<FailedCommand>,
+}
+
+impl FailureSummary {
+    /// Print failed commands with their last lines to stderr
+    pub(super) fn print(&self, colorize: bool) {
+        if self.failures.is_empty() {
+            return;
+        }
+
+        let killed = self.killed_count();
+        let level = if killed == self.failures.len() {
+            MessageLevel::Warning
+        } else {
+            MessageLevel::Error
+        };
+        eprint_message(colorize, level, format_args!("{}", self.header()));
+
+        for failed in &self.failures {
+            failed.print();
+        }
+    }
+
+    fn killed_count(&self) -> usize {
+        self.failures.iter().filter(|failed| failed.killed).count()
+    }
+
+    /// Like `2 of 20 commands failed, 1 killed by runcc`
+    fn header(&self) -> String {
+        let killed = self.killed_count();
+        let failed = self.failures.len() - killed;
+        if killed == 0 {
+            format!("{} of {} commands failed", failed, self.exited)
+        } else {
+            format!(
+                "{} of {} commands failed, {} killed by runcc",
+                failed, self.exited, killed
+            )
+        }
+    }
+}
+
+impl FailedCommand {
+    /// Print the exit status and the last lines
+    fn print(&self) {
+        let tail = self.tail.as_ref().map(|tail| tail.lock().unwrap());
+        let lines: Vec<&str> = tail.iter().flat_map(|tail| tail.lines()).collect();
+
+        if lines.is_empty() {
+            OutputStream::Stderr.write_line(&format!("{} {}", self.label, self.status));
+            return;
+        }
+
+        OutputStream::Stderr.write_line(&format!(
+            "{} {}, last {} lines:",
+            self.label,
+            self.status,
+            lines.len()
+        ));
+        for line in lines {
+            OutputStream::Stderr.write_line(&format!("  {}", line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FailedCommand, FailureSummary, TailBuffer};
+
+    #[test]
+    fn keep_last_lines() {
+        let mut tail = TailBuffer::new(2);
+        for line in ["a", "b", "c"] {
+            tail.push(line);
+        }
+        assert_eq!(tail.lines().collect::<Vec<_>>(), ["b", "c"]);
+
+        let mut tail = TailBuffer::new(0);
+        tail.push("a");
+        assert_eq!(tail.lines().count(), 0);
+    }
+
+    #[test]
+    fn count_killed_commands() {
+        let failed = |killed| FailedCommand {
+            label: "[api]".to_string(),
+            status: "exited with status code 1".to_string(),
+            killed,
+            tail: None,
+        };
+        let mut summary = FailureSummary {
+            exited: 3,
+            failures: vec![failed(false)],
+        };
+        assert_eq!(summary.header(), "1 of 3 commands failed");
+
+        summary.failures.push(failed(true));
+        assert_eq!(
+            summary.header(),
+            "1 of 3 commands failed, 1 killed by runcc"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    fmt::{Display, Write},
+    time::{Instant, SystemTime},
+};
+
+use chrono::{
+    format::{Fixed, Item, Numeric, Pad, StrftimeItems},
+    DateTime, Local, SecondsFormat, TimeDelta, Utc,
+};
+
+use crate::TimestampMode;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TimestampFormatError {
+    /// The format has unknown or incomplete specifiers
+    Invalid(String),
+    /// Only time specifiers can be used to format time since start
+    DateSinceStart(String),
+}
+
+impl std::error::Error for TimestampFormatError {}
+
+impl Display for TimestampFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimestampFormatError::Invalid(s) => write!(f, "invalid timestamp format \"{}\"", s),
+            TimestampFormatError::DateSinceStart(s) => write!(
+                f,
+                "timestamp format \"{}\" can only have %H %M %S %T and fractions of a second for time since start",
+                s
+            ),
+        }
+    }
+}
+
+/// The time when a line is read
+#[derive(Debug, Clone, Copy)]
+pub struct LineTime {
+    pub instant: Instant,
+    pub system: SystemTime,
+}
+
+impl LineTime {
+    pub fn now() -> Self {
+        Self {
+            instant: Instant::now(),
+            system: SystemTime::now(),
+        }
+    }
+}
+
+/// Format of timestamps, with strftime specifiers of [`chrono`],
+/// like `%F` `%T` `%.3f` `%z` and `%s`.
+///
+/// For time since start, `%H` is total hours and date specifiers are not allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampFormat {
+    mode: TimestampMode,
+    items: Vec<Item<'static>>,
+}
+
+/// Whether `item` can be used to format time since start
+fn is_time_item(item: &Item) -> bool {
+    match item {
+        Item::Literal(_) | Item::OwnedLiteral(_) | Item::Space(_) | Item::OwnedSpace(_) => true,
+        Item::Numeric(numeric, _) => matches!(
+            numeric,
+            Numeric::Hour | Numeric::Minute | Numeric::Second | Numeric::Nanosecond
+        ),
+        // `%3f` `%6f` `%9f` are internal items
+        Item::Fixed(fixed) => matches!(
+            fixed,
+            Fixed::Nanosecond
+                | Fixed::Nanosecond3
+                | Fixed::Nanosecond6
+                | Fixed::Nanosecond9
+                | Fixed::Internal(_)
+        ),
+        Item::Error => false,
+    }
+}
+
+impl TimestampFormat {
+    pub const DEFAULT: &'static str = "%T%.3f";
+
+    pub fn parse(mode: TimestampMode, format: &str) -> Result<Self, TimestampFormatError> {
+        let items = StrftimeItems::new(format)
+            .parse_to_owned()
+            .map_err(|_| TimestampFormatError::Invalid(format.to_string()))?;
+
+        if mode == TimestampMode::SinceStart && !items.iter().all(is_time_item) {
+            return Err(TimestampFormatError::DateSinceStart(format.to_string()));
+        }
+
+        Ok(Self { mode, items })
+    }
+
+    /// Format the time of a line, where `started_at` is when runcc started
+    pub fn format(&self, time: &LineTime, started_at: Instant) -> String {
+        let mut res = String::new();
+
+        // formatting only fails with items which need a time zone for time since start
+        let _ = match self.mode {
+            TimestampMode::Wall => write!(
+                res,
+                "{}",
+                DateTime::<Local>::from(time.system).format_with_items(self.items.iter())
+            ),
+            TimestampMode::SinceStart => {
+                let elapsed = time.instant.saturating_duration_since(started_at);
+                let hours = elapsed.as_secs() / 3600;
+                let time = DateTime::UNIX_EPOCH.naive_utc()
+                    + TimeDelta::from_std(elapsed).unwrap_or(TimeDelta::MAX);
+
+                // `%H` is total hours, which can be more than 23
+                let items = self.items.iter().map(|item| match item {
+                    Item::Numeric(Numeric::Hour, pad) => Item::OwnedLiteral(
+                        match pad {
+                            Pad::Zero => format!("{:02}", hours),
+                            Pad::Space => format!("{:2}", hours),
+                            Pad::None => hours.to_string(),
+                        }
+                        .into(),
+                    ),
+                    item => item.clone(),
+                });
+
+                write!(res, "{}", time.format_with_items(items))
+            }
+        };
+
+        res
+    }
+}
+
+/// Format `time` in RFC 3339 in UTC with milliseconds, like `2022-01-08T12:34:56.789Z`
+pub fn format_rfc3339_utc(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_rfc3339_utc, LineTime, TimestampFormat, TimestampFormatError};
+    use crate::TimestampMode;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn format_rfc3339() {
+        assert_eq!(
+            format_rfc3339_utc(
+                UNIX_EPOCH + Duration::from_millis(19_000 * 86_400_000 + 45_296_789)
+            ),
+            "2022-01-08T12:34:56.789Z"
+        );
+    }
+
+    #[test]
+    fn format_since_start() {
+        let started_at = std::time::Instant::now();
+        let time = LineTime {
+            instant: started_at + Duration::from_millis(3_723_045),
+            system: std::time::SystemTime::now(),
+        };
+
+        for (format, res) in [
+            (TimestampFormat::DEFAULT, "01:02:03.045"),
+            ("+%H%%%M %6f", "+01%02 045000"),
+            ("%S%3f", "03045"),
+            ("%-H:%M", "1:02"),
+        ] {
+            let format = TimestampFormat::parse(TimestampMode::SinceStart, format).unwrap();
+            assert_eq!(format.format(&time, started_at), res);
+        }
+    }
+
+    #[test]
+    fn parse_timestamp_format_fail() {
+        assert_eq!(
+            TimestampFormat::parse(TimestampMode::Wall, "%Q"),
+            Err(TimestampFormatError::Invalid("%Q".to_string()))
+        );
+        assert_eq!(
+            TimestampFormat::parse(TimestampMode::SinceStart, "%F %T"),
+            Err(TimestampFormatError::DateSinceStart("%F %T".to_string()))
+        );
+    }
+
+    #[test]
+    fn format_wall_date() {
+        let format = TimestampFormat::parse(TimestampMode::Wall, "%F %s").unwrap();
+        let time = LineTime::now();
+        let formatted = format.format(&time, time.instant);
+        assert_eq!(formatted.len(), "2022-01-08 ".len() + 10, "{}", formatted);
+    }
+}
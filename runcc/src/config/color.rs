@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, io::IsTerminal};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Colorize if stdout is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn should_colorize(self) -> bool {
+        match self {
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+
+    /// Env vars to tell commands whether to colorize output,
+    /// because commands can't detect terminal by themselves when their output is piped to runcc.
+    pub fn child_env_defaults(self) -> HashMap<String, String> {
+        let envs: &[(&str, &str)] = if self.should_colorize() {
+            &[
+                ("CARGO_TERM_COLOR", "always"),
+                // yarn force color https://classic.yarnpkg.com/en/docs/cli/#toc-verbose
+                ("FORCE_COLOR", "true"),
+            ]
+        } else {
+            &[("CARGO_TERM_COLOR", "never"), ("NO_COLOR", "1")]
+        };
+
+        envs.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+const COLOR_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright_black",
+    "bright_red",
+    "bright_green",
+    "bright_yellow",
+    "bright_blue",
+    "bright_magenta",
+    "bright_cyan",
+    "bright_white",
+];
+
+/// One of 256 terminal colors.
+///
+/// In config files, it can be a name like `red` and `bright_blue`,
+/// or a number from 0 to 255.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "ColorInput", into = "ColorInput")]
+pub struct Color(pub u8);
+
+impl Color {
+    pub const RED: Color = Color(1);
+    pub const GREEN: Color = Color(2);
+    pub const YELLOW: Color = Color(3);
+    pub const BLUE: Color = Color(4);
+    pub const MAGENTA: Color = Color(5);
+    pub const CYAN: Color = Color(6);
+    pub const BRIGHT_BLACK: Color = Color(8);
+    pub const BRIGHT_GREEN: Color = Color(10);
+    pub const BRIGHT_YELLOW: Color = Color(11);
+    pub const BRIGHT_BLUE: Color = Color(12);
+    pub const BRIGHT_MAGENTA: Color = Color(13);
+    pub const BRIGHT_CYAN: Color = Color(14);
+
+    /// Colors for labels of commands without an explicit color.
+    ///
+    /// Red and gray are left out for messages of runcc itself.
+    pub const LABEL_PALETTE: [Color; 10] = [
+        Color::CYAN,
+        Color::MAGENTA,
+        Color::GREEN,
+        Color::YELLOW,
+        Color::BLUE,
+        Color::BRIGHT_CYAN,
+        Color::BRIGHT_MAGENTA,
+        Color::BRIGHT_GREEN,
+        Color::BRIGHT_YELLOW,
+        Color::BRIGHT_BLUE,
+    ];
+
+    /// Color from [`Color::LABEL_PALETTE`] for the `index`-th command
+    pub fn from_palette(index: usize) -> Self {
+        Self::LABEL_PALETTE[index % Self::LABEL_PALETTE.len()]
+    }
+
+    /// ANSI escape sequence to set this color as foreground
+    pub fn ansi_prefix(self) -> String {
+        match self.0 {
+            n @ 0..=7 => format!("\x1B[{}m", 30 + n),
+            n @ 8..=15 => format!("\x1B[{}m", 90 + n - 8),
+            n => format!("\x1B[38;5;{}m", n),
+        }
+    }
+
+    /// Wrap `s` with ANSI escape sequences to display it in this color
+    pub fn paint(self, s: &str) -> String {
+        format!("{}{}\x1B[0m", self.ansi_prefix(), s)
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match COLOR_NAMES.get(self.0 as usize) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidColorError(String);
+
+impl std::error::Error for InvalidColorError {}
+
+impl Display for InvalidColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid color {:?}, expected a name like red and bright_blue, or a number from 0 to 255",
+            self.0
+        )
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = InvalidColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(i) = COLOR_NAMES.iter().position(|name| *name == s) {
+            return Ok(Color(i as u8));
+        }
+
+        s.parse()
+            .map(Color)
+            .map_err(|_| InvalidColorError(s.to_string()))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum ColorInput {
+    Code(u8),
+    Name(String),
+}
+
+impl std::convert::TryFrom<ColorInput> for Color {
+    type Error = InvalidColorError;
+
+    fn try_from(value: ColorInput) -> Result<Self, Self::Error> {
+        match value {
+            ColorInput::Code(code) => Ok(Color(code)),
+            ColorInput::Name(name) => name.parse(),
+        }
+    }
+}
+
+impl From<Color> for ColorInput {
+    fn from(color: Color) -> Self {
+        if (color.0 as usize) < COLOR_NAMES.len() {
+            ColorInput::Name(color.to_string())
+        } else {
+            ColorInput::Code(color.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, ColorChoice};
+
+    #[test]
+    fn parse_color_choice() {
+        for (input, val) in [
+            ("auto", ColorChoice::Auto),
+            ("always", ColorChoice::Always),
+            ("never", ColorChoice::Never),
+        ] {
+            assert_eq!(input.parse::<ColorChoice>().unwrap(), val);
+        }
+
+        assert!("Always".parse::<ColorChoice>().is_err());
+    }
+
+    #[test]
+    fn child_env_defaults() {
+        assert_eq!(
+            ColorChoice::Always.child_env_defaults()["CARGO_TERM_COLOR"],
+            "always"
+        );
+        assert_eq!(
+            ColorChoice::Never.child_env_defaults()["CARGO_TERM_COLOR"],
+            "never"
+        );
+    }
+
+    #[test]
+    fn parse_color() {
+        for (input, color) in [
+            ("red", Color::RED),
+            ("bright_blue", Color::BRIGHT_BLUE),
+            ("208", Color(208)),
+        ] {
+            assert_eq!(input.parse::<Color>().unwrap(), color);
+            assert_eq!(serde_yaml::from_str::<Color>(input).unwrap(), color);
+        }
+
+        assert!("orange".parse::<Color>().is_err());
+        assert!(serde_yaml::from_str::<Color>("256").is_err());
+
+        assert_eq!(Color::RED.paint("a"), "\x1B[31ma\x1B[0m");
+        assert_eq!(Color::BRIGHT_BLUE.paint("a"), "\x1B[94ma\x1B[0m");
+        assert_eq!(Color(208).paint("a"), "\x1B[38;5;208ma\x1B[0m");
+    }
+}
@@ -1,21 +1,129 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+};
+
 use serde::{Deserialize, Serialize};
 
-use crate::env::match_program_with_envs;
+use crate::{env::match_program_with_envs, interpolate, interpolate_script, InterpolateError};
 
 #[non_exhaustive]
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub struct CommandConfig {
     pub program: String,
     pub args: Option<Vec<String>>,
     pub label: Option<String>,
     pub envs: Option<Vec<(String, String)>>,
     pub cwd: Option<String>,
+    /// Dotenv files, relative to `cwd`.
+    ///
+    /// They are loaded before variables are interpolated,
+    /// so variables in `cwd` are not resolved for the paths of env files.
+    ///
+    /// See [`CommandConfig::load_env_files`].
+    pub env_file: Option<Vec<String>>,
+    /// Don't inherit any env vars from runcc process
+    pub env_clear: Option<bool>,
+    /// Env vars not to inherit from runcc process.
+    ///
+    /// A name ending with `*` matches all env vars with the prefix, like `CARGO_*`.
+    pub env_remove: Option<Vec<String>>,
+    /// Only inherit these env vars from runcc process.
+    ///
+    /// A name ending with `*` matches all env vars with the prefix, like `CARGO_*`.
+    pub env_inherit: Option<Vec<String>>,
+    /// Color of the label in logs.
+    ///
+    /// Defaults to a color from [`Color::LABEL_PALETTE`](super::Color::LABEL_PALETTE)
+    /// by the index of this command.
+    pub color: Option<super::Color>,
+    /// File to write stdout and stderr of this command, without prefix.
+    ///
+    /// Relative to the current working directory of runcc.
+    /// Defaults to `{label}.log` in `log_dir` of [`RunConfig`](super::RunConfig).
+    pub log_file: Option<String>,
+    /// Encoding of output of this command, defaults to UTF-8
+    pub encoding: Option<super::Encoding>,
+    /// Rules to hide, only show or highlight output lines of this command in logs
+    pub output: Option<super::OutputRules>,
+    /// Where to write stdout of this command, defaults to `show`
+    pub stdout: Option<super::OutputVisibility>,
+    /// Where to write stderr of this command, defaults to `show`
+    pub stderr: Option<super::OutputVisibility>,
+}
+
+/// Whether env var `name` matches `pattern` like `PATH` or `CARGO_*`
+fn env_name_matches(pattern: &str, name: &OsStr) -> bool {
+    let name = name.to_string_lossy();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// Whether env var `name` matches any of `patterns`
+fn env_name_matches_any(patterns: Option<&[String]>, name: &OsStr) -> bool {
+    patterns.is_some_and(|patterns| {
+        patterns
+            .iter()
+            .any(|pattern| env_name_matches(pattern, name))
+    })
+}
+
+/// How a command inherits env vars from runcc process
+#[derive(Debug, PartialEq, Eq)]
+struct EnvIsolation {
+    /// Clear all env vars, and then set `inherit`
+    clear: bool,
+    inherit: Vec<(OsString, OsString)>,
+    remove: Vec<OsString>,
+}
+
+impl EnvIsolation {
+    /// `parent_envs` are the env vars of runcc process.
+    ///
+    /// Env vars are cleared if `env_clear` is true,
+    /// or `env_inherit` is set and `env_clear` is not false.
+    fn new(
+        env_clear: Option<bool>,
+        env_remove: Option<&[String]>,
+        env_inherit: Option<&[String]>,
+        parent_envs: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Self {
+        let clear = env_clear.unwrap_or(env_inherit.is_some());
+
+        let mut inherit = vec![];
+        let mut remove = vec![];
+        for (name, value) in parent_envs {
+            if env_name_matches_any(env_remove, &name) {
+                if !clear {
+                    remove.push(name);
+                }
+            } else if clear && env_name_matches_any(env_inherit, &name) {
+                inherit.push((name, value));
+            }
+        }
+
+        Self {
+            clear,
+            inherit,
+            remove,
+        }
+    }
 }
 
 #[non_exhaustive]
 #[derive(Debug, Default)]
 pub struct CommandConfigFromScriptOptions {
     pub windows_call_cmd_with_env: super::WindowsCallCmdWithEnv,
+    /// Arguments to replace placeholders like `{1}` in commands.
+    ///
+    /// See [`replace_placeholders`](super::replace_placeholders).
+    /// Placeholders are kept as is if this is `None`.
+    pub passthrough_args: Option<Vec<String>>,
 }
 
 macro_rules! def_into_command_and_label {
@@ -32,6 +140,16 @@ macro_rules! def_into_command_and_label {
                 label,
                 envs,
                 cwd,
+                env_file: _,
+                env_clear,
+                env_remove,
+                env_inherit,
+                color: _,
+                log_file: _,
+                encoding: _,
+                output: _,
+                stdout: _,
+                stderr: _,
             } = self;
 
             let mut command = <$cmd_type>::new(&program);
@@ -44,6 +162,23 @@ macro_rules! def_into_command_and_label {
                 command.args(args);
             }
 
+            let isolation = EnvIsolation::new(
+                env_clear,
+                env_remove.as_deref(),
+                env_inherit.as_deref(),
+                std::env::vars_os(),
+            );
+
+            if isolation.clear {
+                command.env_clear();
+            }
+
+            command.envs(isolation.inherit);
+
+            for name in isolation.remove {
+                command.env_remove(name);
+            }
+
             if let Some(envs) = inherited_envs {
                 command.envs(envs);
             }
@@ -69,19 +204,22 @@ impl CommandConfig {
     pub fn from_script(script: &str, options: &CommandConfigFromScriptOptions) -> CommandConfig {
         let script = script.trim();
 
-        let (program, envs) = match_program_with_envs(script);
+        let label = match_program_with_envs(script).0.to_string();
 
-        let program = program.to_string();
-        let envs = if let Some(envs) = envs {
-            Some(
-                envs.into_iter()
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-                    .collect(),
-            )
-        } else {
-            None
+        let script = match &options.passthrough_args {
+            Some(args) => super::replace_placeholders(script, args, super::shell_quote),
+            None => script.into(),
         };
 
+        let (program, envs) = match_program_with_envs(&script);
+
+        let program = program.to_string();
+        let envs = envs.map(|envs| {
+            envs.into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        });
+
         if cfg!(target_os = "windows") {
             let with_env = &options.windows_call_cmd_with_env;
 
@@ -95,9 +233,9 @@ impl CommandConfig {
             let mut cmd = CommandConfig {
                 program: "cmd".to_string(),
                 args: Some(vec!["/C".to_string(), arg]),
-                label: Some(program.clone()),
+                label: Some(label),
                 envs,
-                cwd: None,
+                ..Default::default()
             };
 
             if let Some(env) = env {
@@ -108,10 +246,10 @@ impl CommandConfig {
         } else {
             CommandConfig {
                 program: "sh".to_string(),
-                args: Some(vec!["-c".to_string(), program.clone()]),
-                label: Some(program),
+                args: Some(vec!["-c".to_string(), program]),
+                label: Some(label),
                 envs,
-                cwd: None,
+                ..Default::default()
             }
         }
     }
@@ -120,31 +258,402 @@ impl CommandConfig {
         CommandConfig {
             program,
             args,
-            label: None,
-            envs: None,
-            cwd: None,
+            ..Default::default()
+        }
+    }
+
+    /// Index in `args` of the script run by a shell, like `sh -c <script>` or `cmd /C <script>`.
+    ///
+    /// Command scripts are run this way, see [`from_script`](Self::from_script).
+    fn script_arg_index(&self) -> Option<usize> {
+        let shell = Path::new(&self.program).file_stem()?.to_string_lossy();
+        let flag = match self.args.as_deref()? {
+            [flag, _script, ..] => flag,
+            _ => return None,
+        };
+
+        let is_script = match shell.to_ascii_lowercase().as_str() {
+            "sh" | "bash" | "dash" | "zsh" | "ksh" => flag == "-c",
+            "cmd" => flag.eq_ignore_ascii_case("/C"),
+            _ => false,
+        };
+
+        is_script.then_some(1)
+    }
+
+    /// The log file of this command, which is `log_file`,
+    /// or `{label}.log` in `log_dir` of [`RunConfig`](super::RunConfig).
+    ///
+    /// `None` if neither is set.
+    pub fn log_file_path(&self, log_dir: Option<&str>) -> Option<PathBuf> {
+        match (&self.log_file, log_dir) {
+            (Some(log_file), _) => Some(PathBuf::from(log_file)),
+            (None, Some(log_dir)) => {
+                Some(super::log_file_in_dir(log_dir, &self.label_or_default()))
+            }
+            (None, None) => None,
         }
     }
 
+    /// Whether stdout or stderr is only written to the log file
+    pub fn writes_to_log_file_only(&self) -> bool {
+        self.stdout == Some(super::OutputVisibility::File)
+            || self.stderr == Some(super::OutputVisibility::File)
+    }
+
+    /// Display width of the label in terminals
     pub fn label_length(&self) -> usize {
-        match &self.label {
-            None => {
-                self.program.len()
-                    + self
-                        .args
-                        .as_ref()
-                        .map_or(0, |args| args.iter().map(|arg| arg.len() + 1).sum())
+        crate::unicode::str_width(&self.label_or_default())
+    }
+
+    /// Replace placeholders like `{1}` in `program` and `args` with `args` passed through.
+    ///
+    /// See [`replace_placeholders_in_args`](super::replace_placeholders_in_args).
+    /// In the script of a shell like `sh -c <script>`,
+    /// arguments are quoted with [`shell_quote`](super::shell_quote).
+    pub fn replace_placeholders(&mut self, passthrough_args: &[String]) {
+        if let Cow::Owned(program) =
+            super::replace_placeholders(&self.program, passthrough_args, super::no_quote)
+        {
+            self.program = program;
+        }
+
+        let script_arg_index = self.script_arg_index();
+
+        if let Some(mut args) = self.args.take() {
+            // the script is taken out so that `{@}` in it is not expanded to multiple args
+            let script = script_arg_index.map(|i| std::mem::take(&mut args[i]));
+            let mut args = super::replace_placeholders_in_args(args, passthrough_args);
+
+            if let (Some(i), Some(script)) = (script_arg_index, script) {
+                args[i] =
+                    super::replace_placeholders(&script, passthrough_args, super::shell_quote)
+                        .into_owned();
             }
-            Some(label) => label.len(),
+
+            self.args = Some(args);
+        }
+    }
+
+    /// Inherit `env_clear`, `env_remove` and `env_inherit` for all commands.
+    ///
+    /// `env_clear` of this command takes precedence.
+    /// `env_remove` and `env_inherit` are merged.
+    pub fn inherit_env_isolation(
+        &mut self,
+        env_clear: Option<bool>,
+        env_remove: Option<&[String]>,
+        env_inherit: Option<&[String]>,
+    ) {
+        if self.env_clear.is_none() {
+            self.env_clear = env_clear;
+        }
+
+        if let Some(env_remove) = env_remove {
+            self.env_remove
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(env_remove);
+        }
+
+        if let Some(env_inherit) = env_inherit {
+            self.env_inherit
+                .get_or_insert_with(Vec::new)
+                .extend_from_slice(env_inherit);
+        }
+    }
+
+    /// The label of this command.
+    /// Defaults to program and args joined with spaces.
+    pub fn label_or_default(&self) -> Cow<'_, str> {
+        match &self.label {
+            Some(label) => label.into(),
+            None => match &self.args {
+                Some(args) => format!("{} {}", self.program, args.join(" ")).into(),
+                None => self.program.as_str().into(),
+            },
         }
     }
 
     pub fn env(&mut self, env: (String, String)) -> &mut Self {
-        self.envs.get_or_insert_with(|| vec![]).push(env);
+        self.envs.get_or_insert_with(Vec::new).push(env);
         self
     }
 
+    /// Read envs from `env_file` into `envs`.
+    ///
+    /// Envs from env files have lower precedence than `envs`,
+    /// and later env files have higher precedence.
+    /// Env files which don't exist are ignored.
+    ///
+    /// `env_file` is ignored by `into_command_and_label` and `into_tokio_command_and_label`,
+    /// so this should be called before them,
+    /// and before [`interpolate`](Self::interpolate) to interpolate envs from env files.
+    pub fn load_env_files(&mut self) -> io::Result<()> {
+        let env_file = match self.env_file.take() {
+            Some(env_file) => env_file,
+            None => return Ok(()),
+        };
+
+        let dir = Path::new(self.cwd.as_deref().unwrap_or(""));
+        let mut envs = vec![];
+        for file in env_file {
+            if let Some(file_envs) = crate::read_env_file(&dir.join(file))? {
+                envs.extend(file_envs);
+            }
+        }
+
+        if let Some(own_envs) = self.envs.take() {
+            envs.extend(own_envs);
+        }
+
+        if !envs.is_empty() {
+            self.envs = Some(envs);
+        }
+
+        Ok(())
+    }
+
+    /// Interpolate variables in `program`, `args`, `cwd` and values of `envs`.
+    ///
+    /// Variables are resolved from `envs` of this command, then `envs` of the run,
+    /// and then the env vars of current process.
+    /// See [`interpolate`](crate::interpolate) for the syntax.
+    /// The script of a shell like `sh -c <script>` is interpolated with
+    /// [`interpolate_script`](crate::interpolate_script), which keeps shell syntax,
+    /// and values are quoted with [`shell_quote`](super::shell_quote).
+    pub fn interpolate(
+        &mut self,
+        envs: Option<&HashMap<String, String>>,
+    ) -> Result<(), InterpolateError> {
+        // later envs take precedence, like inline envs over env files
+        let own_envs = self.envs.clone().unwrap_or_default();
+        let lookup = |name: &str| {
+            own_envs
+                .iter()
+                .rev()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+                .or_else(|| envs.and_then(|envs| envs.get(name).cloned()))
+                .or_else(|| std::env::var(name).ok())
+        };
+
+        fn interpolate_in_place<F: Fn(&str) -> Option<String>>(
+            s: &mut String,
+            lookup: &F,
+            is_script: bool,
+        ) -> Result<(), InterpolateError> {
+            let res = if is_script {
+                let lookup = |name: &str| lookup(name).map(|v| super::shell_quote(&v).into_owned());
+                interpolate_script(s, &lookup)?
+            } else {
+                interpolate(s, lookup)?
+            };
+            if let Cow::Owned(res) = res {
+                *s = res;
+            }
+            Ok(())
+        }
+
+        let script_arg_index = self.script_arg_index();
+
+        interpolate_in_place(&mut self.program, &lookup, false)?;
+
+        for (i, arg) in self.args.iter_mut().flatten().enumerate() {
+            interpolate_in_place(arg, &lookup, Some(i) == script_arg_index)?;
+        }
+
+        if let Some(cwd) = &mut self.cwd {
+            interpolate_in_place(cwd, &lookup, false)?;
+        }
+
+        for (_, value) in self.envs.iter_mut().flatten() {
+            interpolate_in_place(value, &lookup, false)?;
+        }
+
+        Ok(())
+    }
+
     def_into_command_and_label! {into_command_and_label->std::process::Command}
 
     def_into_command_and_label! {into_tokio_command_and_label->tokio::process::Command}
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::{CommandConfig, EnvIsolation};
+    use crate::OutputVisibility;
+
+    #[cfg(not(windows))]
+    #[test]
+    fn interpolate_script_keeps_shell_syntax() {
+        let envs = [("PORT".to_string(), "3000".to_string())].into();
+
+        let mut cmd = CommandConfig::from_script(
+            "for f in *.txt; do serve --port=${PORT} ${f%.txt} ${#arr[@]} ${f}; done",
+            &Default::default(),
+        );
+        cmd.interpolate(Some(&envs)).unwrap();
+        assert_eq!(
+            cmd.args.unwrap(),
+            [
+                "-c",
+                "for f in *.txt; do serve --port=3000 ${f%.txt} ${#arr[@]} ${f}; done"
+            ]
+        );
+
+        let mut cmd = CommandConfig::from_program_args(
+            "echo".to_string(),
+            Some(vec![
+                "${PORT}".to_string(),
+                "${RUNCC_TEST_UNSET}".to_string(),
+            ]),
+        );
+        cmd.interpolate(Some(&envs)).unwrap();
+        assert_eq!(cmd.args.unwrap(), ["3000", ""]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn interpolate_script_quotes_values() {
+        let envs = [("NAME".to_string(), "a b;$(id)".to_string())].into();
+
+        let mut cmd = CommandConfig::from_script("echo ${NAME}", &Default::default());
+        cmd.interpolate(Some(&envs)).unwrap();
+        assert_eq!(cmd.args.unwrap(), ["-c", "echo 'a b;$(id)'"]);
+
+        // inline envs before the script take precedence
+        let envs = [("PORT".to_string(), "1".to_string())].into();
+        let mut cmd =
+            CommandConfig::from_script("PORT=3000 serve --port ${PORT}", &Default::default());
+        cmd.interpolate(Some(&envs)).unwrap();
+        assert_eq!(cmd.args.unwrap(), ["-c", "serve --port 3000"]);
+    }
+
+    #[test]
+    fn interpolate_own_envs_first() {
+        let envs = [
+            ("PORT".to_string(), "1".to_string()),
+            ("HOST".to_string(), "run".to_string()),
+            ("PATH".to_string(), "run".to_string()),
+        ]
+        .into();
+
+        let mut cmd = CommandConfig::from_program_args(
+            "serve".to_string(),
+            Some(vec![
+                "${PORT}".to_string(),
+                "${HOST}".to_string(),
+                "${PATH}".to_string(),
+                "${RUNCC_TEST_ONLY_OWN}".to_string(),
+            ]),
+        );
+        cmd.envs = Some(vec![
+            ("PORT".to_string(), "2".to_string()),
+            ("PORT".to_string(), "3000".to_string()),
+            ("RUNCC_TEST_ONLY_OWN".to_string(), "own".to_string()),
+        ]);
+        cmd.interpolate(Some(&envs)).unwrap();
+        assert_eq!(cmd.args.unwrap(), ["3000", "run", "run", "own"]);
+    }
+
+    #[test]
+    fn resolve_log_file_path() {
+        let mut cmd = CommandConfig::from_program_args(
+            "cargo".to_string(),
+            Some(vec!["run".to_string(), "-p".to_string(), "a/b".to_string()]),
+        );
+        assert_eq!(cmd.log_file_path(None), None);
+        assert_eq!(
+            cmd.log_file_path(Some("logs")),
+            Some(std::path::Path::new("logs").join("cargo run -p a_b.log"))
+        );
+
+        cmd.label = Some("api".to_string());
+        assert_eq!(
+            cmd.log_file_path(Some("logs")),
+            Some(std::path::Path::new("logs").join("api.log"))
+        );
+
+        cmd.log_file = Some("api-server.log".to_string());
+        assert_eq!(
+            cmd.log_file_path(Some("logs")),
+            Some("api-server.log".into())
+        );
+
+        assert!(!cmd.writes_to_log_file_only());
+        cmd.stderr = Some(OutputVisibility::File);
+        assert!(cmd.writes_to_log_file_only());
+    }
+
+    #[test]
+    fn env_isolation() {
+        let parent_envs = || {
+            [("A", "a"), ("B", "b"), ("CARGO_X", "x"), ("PATH", "/bin")]
+                .map(|(k, v)| (OsString::from(k), OsString::from(v)))
+        };
+        let names = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+        let envs = |envs: &[(&str, &str)]| {
+            envs.iter()
+                .map(|(k, v)| (OsString::from(k), OsString::from(v)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            EnvIsolation::new(None, Some(&names(&["A", "CARGO_*"])), None, parent_envs()),
+            EnvIsolation {
+                clear: false,
+                inherit: vec![],
+                remove: vec!["A".into(), "CARGO_X".into()],
+            }
+        );
+
+        assert_eq!(
+            EnvIsolation::new(
+                None,
+                Some(&names(&["CARGO_*"])),
+                Some(&names(&["PATH", "CARGO_*"])),
+                parent_envs()
+            ),
+            EnvIsolation {
+                clear: true,
+                inherit: envs(&[("PATH", "/bin")]),
+                remove: vec![],
+            }
+        );
+
+        assert_eq!(
+            EnvIsolation::new(Some(true), None, None, parent_envs()),
+            EnvIsolation {
+                clear: true,
+                inherit: vec![],
+                remove: vec![],
+            }
+        );
+
+        // env_clear: false of a command takes precedence over env_inherit of all commands
+        let mut cmd = CommandConfig::from_program_args("env".to_string(), None);
+        cmd.env_clear = Some(false);
+        cmd.inherit_env_isolation(Some(true), Some(&names(&["B"])), Some(&names(&["PATH"])));
+        assert_eq!(cmd.env_clear, Some(false));
+        assert_eq!(
+            EnvIsolation::new(
+                cmd.env_clear,
+                cmd.env_remove.as_deref(),
+                cmd.env_inherit.as_deref(),
+                parent_envs()
+            ),
+            EnvIsolation {
+                clear: false,
+                inherit: vec![],
+                remove: vec!["B".into()],
+            }
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Text encoding of output of a command.
+///
+/// Invalid bytes are decoded as `U+FFFD REPLACEMENT CHARACTER`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    #[serde(alias = "utf-8")]
+    Utf8,
+    /// ISO-8859-1, where each byte is a char
+    #[serde(alias = "latin-1", alias = "iso-8859-1")]
+    Latin1,
+    /// UTF-16 with a byte order mark, or little endian without it
+    #[serde(alias = "utf-16")]
+    Utf16,
+    #[serde(alias = "utf-16le")]
+    Utf16le,
+    #[serde(alias = "utf-16be")]
+    Utf16be,
+}
+
+impl std::str::FromStr for Encoding {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
@@ -6,16 +6,36 @@ use super::super::command::*;
 #[non_exhaustive]
 #[derive(Deserialize, Serialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum CommandConfigInput {
     Command(String),
     ProgramAndArgs(Vec<String>),
     CommandConfig(CommandConfig),
 }
 
+/// A command in labeled commands, which can also be enabled or disabled by label.
+///
+/// A list of commands can't contain `true` or `false`, since they have no labels.
+#[non_exhaustive]
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum LabeledCommandConfigInput {
+    /// `false` disables the command with this label,
+    /// including the one inherited from extended config files.
+    /// `true` keeps the inherited command,
+    /// or runs the label as program if there is none.
+    Enabled(bool),
+    Command(CommandConfigInput),
+}
+
 impl CommandConfigInput {
     pub fn into_config(self, options: &CommandConfigFromScriptOptions) -> CommandConfig {
-        match self {
-            CommandConfigInput::Command(script) => CommandConfig::from_script(&script, options),
+        let mut config = match self {
+            CommandConfigInput::Command(script) => {
+                // placeholders are replaced with shell quoted args in the script
+                return CommandConfig::from_script(&script, options);
+            }
             CommandConfigInput::ProgramAndArgs(mut names) => {
                 let program = if names.is_empty() {
                     String::new()
@@ -28,7 +48,13 @@ impl CommandConfigInput {
                 )
             }
             CommandConfigInput::CommandConfig(config) => config,
+        };
+
+        if let Some(args) = &options.passthrough_args {
+            config.replace_placeholders(args);
         }
+
+        config
     }
 }
 
@@ -37,28 +63,140 @@ impl CommandConfigInput {
 #[serde(untagged)]
 pub enum CommandConfigsInput {
     Commands(Vec<CommandConfigInput>),
-    LabeledCommands(HashMap<String, Option<CommandConfigInput>>),
+    LabeledCommands(HashMap<String, Option<LabeledCommandConfigInput>>),
+}
+
+impl Default for CommandConfigsInput {
+    fn default() -> Self {
+        Self::Commands(vec![])
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+enum CommandLayer {
+    Set(CommandConfig),
+    Keep(String),
+    Disable(String),
 }
 
 impl CommandConfigsInput {
     pub fn into_configs(self, options: &CommandConfigFromScriptOptions) -> Vec<CommandConfig> {
+        let mut configs = vec![];
+        self.merge_into_configs(&mut configs, options);
+        configs
+    }
+
+    /// Merge commands of `overlay` on top of `self` by label.
+    ///
+    /// A command in `overlay` replaces the command with the same label in `self`.
+    /// Other commands in `overlay` are appended.
+    pub fn merge(self, overlay: Self, options: &CommandConfigFromScriptOptions) -> Self {
+        match (self, overlay) {
+            (Self::LabeledCommands(mut commands), Self::LabeledCommands(overlay)) => {
+                for (label, command) in overlay {
+                    match command {
+                        Some(LabeledCommandConfigInput::Enabled(false)) => {
+                            commands.remove(&label);
+                        }
+                        Some(LabeledCommandConfigInput::Enabled(true)) => {
+                            commands.entry(label).or_insert(None);
+                        }
+                        command => {
+                            commands.insert(label, command);
+                        }
+                    }
+                }
+
+                Self::LabeledCommands(commands)
+            }
+            (base, overlay) => {
+                let mut configs = base.into_configs(options);
+                overlay.merge_into_configs(&mut configs, options);
+
+                Self::Commands(
+                    configs
+                        .into_iter()
+                        .map(|mut config| {
+                            // keep the label when it is merged again
+                            config.label = Some(config.label_or_default().into_owned());
+                            CommandConfigInput::CommandConfig(config)
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    fn into_layers(self, options: &CommandConfigFromScriptOptions) -> Vec<CommandLayer> {
         match self {
             CommandConfigsInput::Commands(commands) => commands
                 .into_iter()
-                .map(|cmd| cmd.into_config(options))
+                .map(|cmd| CommandLayer::Set(cmd.into_config(options)))
                 .collect(),
             CommandConfigsInput::LabeledCommands(map) => map
                 .into_iter()
                 .map(|(label, command)| match command {
-                    Some(command) => {
+                    Some(LabeledCommandConfigInput::Enabled(false)) => CommandLayer::Disable(label),
+                    Some(LabeledCommandConfigInput::Enabled(true)) => CommandLayer::Keep(label),
+                    Some(LabeledCommandConfigInput::Command(command)) => {
                         let mut command: CommandConfig = command.into_config(options);
                         command.label = Some(label);
 
-                        command
+                        CommandLayer::Set(command)
                     }
-                    None => CommandConfig::from_program_args(label, None),
+                    None => CommandLayer::Set(CommandConfig::from_program_args(label, None)),
                 })
                 .collect(),
         }
     }
+
+    fn merge_into_configs(
+        self,
+        configs: &mut Vec<CommandConfig>,
+        options: &CommandConfigFromScriptOptions,
+    ) {
+        let base_len = configs.len();
+        // each inherited command can only be replaced once,
+        // so that commands with the same label in one layer are all kept
+        let mut replaced = vec![false; base_len];
+        let mut disabled = vec![];
+
+        for layer in self.into_layers(options) {
+            let (label, command) = match layer {
+                CommandLayer::Set(command) => (command.label_or_default().into_owned(), command),
+                CommandLayer::Keep(label) => {
+                    if configs[0..base_len]
+                        .iter()
+                        .all(|cmd| cmd.label_or_default() != label)
+                    {
+                        configs.push(CommandConfig::from_program_args(label, None));
+                    }
+                    continue;
+                }
+                CommandLayer::Disable(label) => {
+                    disabled.push(label);
+                    continue;
+                }
+            };
+
+            let index = configs[0..base_len]
+                .iter()
+                .enumerate()
+                .position(|(i, cmd)| !replaced[i] && cmd.label_or_default() == label);
+
+            if let Some(index) = index {
+                replaced[index] = true;
+                configs[index] = command;
+            } else {
+                configs.push(command);
+            }
+        }
+
+        if !disabled.is_empty() {
+            configs.retain(|cmd| {
+                let label = cmd.label_or_default();
+                disabled.iter().all(|disabled| *disabled != label)
+            });
+        }
+    }
 }
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use super::super::{ExitStatusPattern, KillBehavior};
+use super::super::{ExitStatusPattern, InvalidExitStatusPatternError, KillBehavior};
 
 #[derive(Deserialize, Serialize, Debug)]
 #[non_exhaustive]
@@ -9,6 +9,22 @@ pub enum KillBehaviorInputStr {
     WhenAnyExited,
     WhenAnySucceeded,
     WhenAnyFailed,
+    WhenAnyKilledBySignal,
+    WhenAnyCoreDumped,
+    WhenAnySpawnFailed,
+}
+
+/// [`ExitStatusPattern`] in the short syntax like `2..=127`, `!0` and `SIGSEGV`
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(try_from = "String")]
+pub struct ExitStatusPatternStr(pub ExitStatusPattern);
+
+impl std::convert::TryFrom<String> for ExitStatusPatternStr {
+    type Error = InvalidExitStatusPatternError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse().map(ExitStatusPatternStr)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -17,24 +33,36 @@ pub enum KillBehaviorInputStr {
 pub enum KillBehaviorInput {
     Str(KillBehaviorInputStr),
     WhenAnyExitedWithStatus(i32),
+    /// Status codes like `[1, 2]`
+    WhenAnyExitedWithStatusIn(Vec<i32>),
+    WhenAnyExitedWithStatusPatternStr(ExitStatusPatternStr),
+    /// Like `{ WhenAnyExitedWithStatus: { Not: { StatusCode: 0 } } }`
+    WhenAnyExitedWithStatusPattern {
+        #[serde(rename = "WhenAnyExitedWithStatus")]
+        pattern: ExitStatusPattern,
+    },
 }
 
 impl From<KillBehaviorInput> for KillBehavior {
     fn from(val: KillBehaviorInput) -> Self {
-        match val {
+        let pattern = match val {
             KillBehaviorInput::Str(val) => match val {
-                KillBehaviorInputStr::None => KillBehavior::None,
-                KillBehaviorInputStr::WhenAnyExited => KillBehavior::WhenAnyExited,
-                KillBehaviorInputStr::WhenAnySucceeded => {
-                    KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::Success)
-                }
-                KillBehaviorInputStr::WhenAnyFailed => {
-                    KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::Failed)
-                }
+                KillBehaviorInputStr::None => return KillBehavior::None,
+                KillBehaviorInputStr::WhenAnyExited => return KillBehavior::WhenAnyExited,
+                KillBehaviorInputStr::WhenAnySucceeded => ExitStatusPattern::Success,
+                KillBehaviorInputStr::WhenAnyFailed => ExitStatusPattern::Failed,
+                KillBehaviorInputStr::WhenAnyKilledBySignal => ExitStatusPattern::KilledBySignal,
+                KillBehaviorInputStr::WhenAnyCoreDumped => ExitStatusPattern::CoreDumped,
+                KillBehaviorInputStr::WhenAnySpawnFailed => ExitStatusPattern::SpawnFailed,
             },
-            KillBehaviorInput::WhenAnyExitedWithStatus(s) => {
-                KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::StatusCode(s))
+            KillBehaviorInput::WhenAnyExitedWithStatus(s) => ExitStatusPattern::StatusCode(s),
+            KillBehaviorInput::WhenAnyExitedWithStatusIn(codes) => {
+                ExitStatusPattern::StatusCodes(codes)
             }
-        }
+            KillBehaviorInput::WhenAnyExitedWithStatusPatternStr(ExitStatusPatternStr(pattern))
+            | KillBehaviorInput::WhenAnyExitedWithStatusPattern { pattern } => pattern,
+        };
+
+        KillBehavior::WhenAnyExitedWithStatus(pattern)
     }
 }
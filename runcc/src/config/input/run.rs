@@ -2,33 +2,201 @@ use serde::{Deserialize, Serialize};
 use std::cmp;
 use std::collections::HashMap;
 
-use super::super::{run::*, CommandConfig, CommandConfigFromScriptOptions, KillBehavior};
+use super::super::{
+    run::*, ColorChoice, CommandConfig, CommandConfigFromScriptOptions, GroupStyle, KillBehavior,
+    LabelAlign, LabelStyle, LabelTruncate, LogFileOptions, OutputMode, ReportFile, TimestampMode,
+};
 use super::CommandConfigsInput;
 
 #[non_exhaustive]
 #[derive(Deserialize, Serialize)]
 pub struct RunConfigInput {
+    /// Path of the config file to extend, relative to this config file.
+    ///
+    /// The extended config file is merged before this one.
+    pub extends: Option<String>,
+    /// Paths of config files to include, relative to this config file.
+    ///
+    /// Included config files are merged after this one, in order.
+    pub include: Option<Vec<String>>,
+    #[serde(default)]
     pub commands: CommandConfigsInput,
     pub max_label_length: Option<usize>,
+    /// How to truncate labels longer than `max_label_length`
+    pub label_truncate: Option<LabelTruncate>,
+    /// How to align labels shorter than `max_label_length`
+    pub label_align: Option<LabelAlign>,
+    /// How labels are decorated in the default prefix, like `[api]` or `api |`
+    pub label_style: Option<LabelStyle>,
+    /// Show the index of the command before the label, like `[2:api]`
+    pub show_index: Option<bool>,
     pub envs: Option<HashMap<String, String>>,
-    #[serde(default)]
-    pub windows_call_cmd_with_env: super::WindowsCallCmdWithEnv,
-    #[serde(default)]
-    pub kill: KillBehavior,
+    /// Dotenv files for all commands, relative to this config file.
+    pub env_file: Option<Vec<String>>,
+    pub env_clear: Option<bool>,
+    pub env_remove: Option<Vec<String>>,
+    pub env_inherit: Option<Vec<String>>,
+    pub windows_call_cmd_with_env: Option<super::WindowsCallCmdWithEnv>,
+    pub kill: Option<KillBehavior>,
+    pub color: Option<ColorChoice>,
+    /// Env vars set for all commands before `envs`.
+    ///
+    /// Defaults to env vars telling commands whether to colorize output,
+    /// according to `color`.
+    pub child_env_defaults: Option<HashMap<String, String>>,
+    /// Template of the prefix of each output line, like `[{label}]`.
+    ///
+    /// `none` means no prefix.
+    pub prefix: Option<String>,
+    /// Add timestamps to output lines, with `{time}` in `prefix`
+    pub timestamps: Option<TimestampMode>,
+    /// Format of timestamps, like `%H:%M:%S%.3f`
+    pub timestamp_format: Option<String>,
+    /// Directory to write log files of commands without `log_file`,
+    /// relative to the current working directory.
+    pub log_dir: Option<String>,
+    pub log_file_options: Option<LogFileOptions>,
+    pub output: Option<OutputMode>,
+    /// Style of output blocks in grouped output mode
+    pub group_style: Option<GroupStyle>,
+    /// Show lines being written like progress bars in place, in interleaved output mode
+    pub show_progress: Option<bool>,
+    /// Flush a line which is not ended after no output is read for this duration in milliseconds
+    pub partial_line_timeout: Option<u64>,
+    /// Only show output lines matching this regex, for all commands
+    pub grep: Option<String>,
+    /// Print failed commands with this many last lines of output when all commands exit
+    pub tail_on_failure: Option<usize>,
+    /// Print a table of all commands with their statuses and durations when all commands exit
+    pub show_summary: Option<bool>,
+    /// Report files written when all commands exit, like `junit:runcc.xml`
+    pub reports: Option<Vec<ReportFile>>,
+    /// Arguments to replace placeholders like `{1}` in commands,
+    /// usually passed after `--` in cli.
+    #[serde(skip)]
+    pub passthrough_args: Option<Vec<String>>,
+}
+
+fn concat(base: Option<Vec<String>>, overlay: Option<Vec<String>>) -> Option<Vec<String>> {
+    match (base, overlay) {
+        (Some(mut base), Some(overlay)) => {
+            base.extend(overlay);
+            Some(base)
+        }
+        (list, None) | (None, list) => list,
+    }
+}
+
+impl RunConfigInput {
+    /// Merge config layers, from the lowest precedence to the highest.
+    ///
+    /// - commands are merged by label
+    /// - envs are merged
+    /// - env files, `env_remove` and `env_inherit` are concatenated
+    /// - other fields are overridden
+    ///
+    /// `extends` and `include` are not resolved here,
+    /// see [`read::find_layered_config_file`](crate::read::find_layered_config_file).
+    pub fn merge_layers(layers: impl IntoIterator<Item = RunConfigInput>) -> Option<Self> {
+        let layers: Vec<_> = layers.into_iter().collect();
+
+        let options = CommandConfigFromScriptOptions {
+            windows_call_cmd_with_env: layers
+                .iter()
+                .rev()
+                .find_map(|layer| layer.windows_call_cmd_with_env.clone())
+                .unwrap_or_default(),
+            // placeholders are replaced when converting into `RunConfig`
+            passthrough_args: None,
+        };
+
+        layers.into_iter().reduce(|base, overlay| {
+            let envs = match (base.envs, overlay.envs) {
+                (Some(mut envs), Some(overlay)) => {
+                    envs.extend(overlay);
+                    Some(envs)
+                }
+                (envs, None) | (None, envs) => envs,
+            };
+
+            RunConfigInput {
+                extends: None,
+                include: None,
+                commands: base.commands.merge(overlay.commands, &options),
+                max_label_length: overlay.max_label_length.or(base.max_label_length),
+                label_truncate: overlay.label_truncate.or(base.label_truncate),
+                label_align: overlay.label_align.or(base.label_align),
+                label_style: overlay.label_style.or(base.label_style),
+                show_index: overlay.show_index.or(base.show_index),
+                envs,
+                env_file: concat(base.env_file, overlay.env_file),
+                env_clear: overlay.env_clear.or(base.env_clear),
+                env_remove: concat(base.env_remove, overlay.env_remove),
+                env_inherit: concat(base.env_inherit, overlay.env_inherit),
+                windows_call_cmd_with_env: overlay
+                    .windows_call_cmd_with_env
+                    .or(base.windows_call_cmd_with_env),
+                kill: overlay.kill.or(base.kill),
+                color: overlay.color.or(base.color),
+                child_env_defaults: overlay.child_env_defaults.or(base.child_env_defaults),
+                prefix: overlay.prefix.or(base.prefix),
+                timestamps: overlay.timestamps.or(base.timestamps),
+                timestamp_format: overlay.timestamp_format.or(base.timestamp_format),
+                log_dir: overlay.log_dir.or(base.log_dir),
+                log_file_options: overlay.log_file_options.or(base.log_file_options),
+                output: overlay.output.or(base.output),
+                group_style: overlay.group_style.or(base.group_style),
+                show_progress: overlay.show_progress.or(base.show_progress),
+                partial_line_timeout: overlay.partial_line_timeout.or(base.partial_line_timeout),
+                grep: overlay.grep.or(base.grep),
+                tail_on_failure: overlay.tail_on_failure.or(base.tail_on_failure),
+                show_summary: overlay.show_summary.or(base.show_summary),
+                reports: overlay.reports.or(base.reports),
+                passthrough_args: overlay.passthrough_args.or(base.passthrough_args),
+            }
+        })
+    }
 }
 
-impl Into<RunConfig> for RunConfigInput {
-    fn into(self) -> RunConfig {
-        let Self {
+impl From<RunConfigInput> for RunConfig {
+    fn from(val: RunConfigInput) -> Self {
+        let RunConfigInput {
+            extends: _,
+            include: _,
             commands,
             max_label_length,
+            label_truncate,
+            label_align,
+            label_style,
+            show_index,
             envs,
+            env_file,
+            env_clear,
+            env_remove,
+            env_inherit,
             windows_call_cmd_with_env,
             kill,
-        } = self;
+            color,
+            child_env_defaults,
+            prefix,
+            timestamps,
+            timestamp_format,
+            log_dir,
+            log_file_options,
+            output,
+            group_style,
+            show_progress,
+            partial_line_timeout,
+            grep,
+            tail_on_failure,
+            show_summary,
+            reports,
+            passthrough_args,
+        } = val;
 
         let commands: Vec<CommandConfig> = commands.into_configs(&CommandConfigFromScriptOptions {
-            windows_call_cmd_with_env,
+            windows_call_cmd_with_env: windows_call_cmd_with_env.unwrap_or_default(),
+            passthrough_args,
         });
 
         let real_max_label_length = commands
@@ -45,8 +213,154 @@ impl Into<RunConfig> for RunConfigInput {
         RunConfig {
             commands,
             max_label_length,
+            label_truncate: label_truncate.unwrap_or_default(),
+            label_align: label_align.unwrap_or_default(),
+            label_style: label_style.unwrap_or_default(),
+            show_index: show_index.unwrap_or_default(),
             envs,
-            kill,
+            env_file,
+            env_clear,
+            env_remove,
+            env_inherit,
+            kill: kill.unwrap_or_default(),
+            color: color.unwrap_or_default(),
+            child_env_defaults,
+            prefix,
+            timestamps,
+            timestamp_format,
+            log_dir,
+            log_file_options: log_file_options.unwrap_or_default(),
+            output: output.unwrap_or_default(),
+            group_style: group_style.unwrap_or_default(),
+            show_progress: show_progress.unwrap_or_default(),
+            partial_line_timeout,
+            grep,
+            tail_on_failure,
+            show_summary,
+            reports: reports.unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RunConfigInput;
+    use crate::{KillBehavior, RunConfig};
+
+    fn parse(yaml: &str) -> RunConfigInput {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn labels_and_programs(config: &RunConfig) -> Vec<(String, String)> {
+        config
+            .commands
+            .iter()
+            .map(|cmd| (cmd.label_or_default().into_owned(), cmd.program.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn merge_labeled_commands() {
+        let config = RunConfigInput::merge_layers([
+            parse("commands: { api: [cargo, run], web: [yarn, start], db: [docker, compose, up] }\nkill: WhenAnyFailed\nenvs: { A: a, B: b }"),
+            parse("commands: { web: [npm, start], db: false }\nenvs: { B: local }"),
+        ])
+        .unwrap();
+
+        let config: RunConfig = config.into();
+
+        let mut commands = labels_and_programs(&config);
+        commands.sort();
+
+        assert_eq!(
+            commands,
+            [
+                ("api".to_string(), "cargo".to_string()),
+                ("web".to_string(), "npm".to_string()),
+            ]
+        );
+
+        let envs = config.envs.unwrap();
+        assert_eq!(envs["A"], "a");
+        assert_eq!(envs["B"], "local");
+        assert_ne!(config.kill, KillBehavior::None);
+    }
+
+    #[test]
+    fn merge_commands_list() {
+        let config = RunConfigInput::merge_layers([
+            parse("commands: [[echo, '1'], [echo, '2'], [echo, '2']]\nmax_label_length: 3"),
+            parse("commands: { echo 2: [printf, overridden], echo 3: }"),
+            parse("kill: WhenAnyExited"),
+        ])
+        .unwrap();
+
+        let config: RunConfig = config.into();
+
+        assert_eq!(
+            labels_and_programs(&config),
+            [
+                ("echo 1".to_string(), "echo".to_string()),
+                ("echo 2".to_string(), "printf".to_string()),
+                ("echo 2".to_string(), "echo".to_string()),
+                ("echo 3".to_string(), "echo 3".to_string()),
+            ]
+        );
+        assert_eq!(config.max_label_length, 3);
+        assert_eq!(config.kill, KillBehavior::WhenAnyExited);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn quote_passthrough_args_in_merged_scripts() {
+        let mut input = RunConfigInput::merge_layers([
+            parse("commands: { test: 'cargo test {1}', echo: [sh, -c, 'echo {@}'] }"),
+            parse("commands: { lint: 'A={1} cargo clippy {*}' }"),
+        ])
+        .unwrap();
+        input.passthrough_args = Some(vec!["foo; rm -rf /".to_string(), "$(id)".to_string()]);
+
+        let config: RunConfig = input.into();
+
+        let mut scripts: Vec<_> = config
+            .commands
+            .iter()
+            .map(|cmd| {
+                (
+                    cmd.label_or_default().into_owned(),
+                    cmd.args.as_ref().unwrap().join(" "),
+                )
+            })
+            .collect();
+        scripts.sort();
+
+        assert_eq!(
+            scripts,
+            [
+                (
+                    "echo".to_string(),
+                    "-c echo 'foo; rm -rf /' '$(id)'".to_string()
+                ),
+                (
+                    "lint".to_string(),
+                    "-c cargo clippy 'foo; rm -rf / $(id)'".to_string()
+                ),
+                (
+                    "test".to_string(),
+                    "-c cargo test 'foo; rm -rf /'".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_enabled_in_commands_list() {
+        for yaml in ["commands: [true]", "commands: [[echo, '1'], false]"] {
+            assert!(
+                serde_yaml::from_str::<RunConfigInput>(yaml).is_err(),
+                "{}",
+                yaml
+            );
         }
     }
 }
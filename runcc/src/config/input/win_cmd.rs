@@ -26,19 +26,20 @@ impl TryFrom<String> for EnvName {
     }
 }
 
-impl Into<String> for EnvName {
-    fn into(self) -> String {
-        self.0
+impl From<EnvName> for String {
+    fn from(val: EnvName) -> Self {
+        val.0
     }
 }
 
 impl EnvName {
+    #[allow(clippy::inherent_to_string)]
     pub fn to_string(self) -> String {
         self.0
     }
 
     pub fn check_str(s: &str) -> bool {
-        s.len() > 0
+        !s.is_empty()
             && s.chars().enumerate().all(|(i, c)| match c {
                 'a'..='z' | 'A'..='Z' => true,
                 '0'..='9' | '_' => i > 0,
@@ -48,8 +49,9 @@ impl EnvName {
 }
 
 #[non_exhaustive]
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub enum WindowsCallCmdWithEnv {
+    #[default]
     Random,
     EnvName(EnvName),
     Disable,
@@ -79,12 +81,6 @@ impl WindowsCallCmdWithEnv {
     }
 }
 
-impl Default for WindowsCallCmdWithEnv {
-    fn default() -> Self {
-        Self::Random
-    }
-}
-
 /// strictly validate env name [a-zA-Z][a-zA-Z0-9_]*
 #[cfg(test)]
 mod tests {
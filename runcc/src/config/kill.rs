@@ -1,43 +1,172 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
+use std::io;
+use std::ops::RangeInclusive;
+use std::process::ExitStatus;
 
 use super::input::KillBehaviorInput;
+use super::Signal;
+use crate::run::ExitStatusReport;
 
+/// Which exit statuses of a command to match, like in [`KillBehavior::WhenAnyExitedWithStatus`].
+///
+/// It can also be parsed from a short syntax, like `2..=127`, `1,2`, `!0` and `SIGSEGV`.
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
 pub enum ExitStatusPattern {
     Success,
+    /// Exited unsuccessfully, or failed to spawn or to be waited
     Failed,
     StatusCode(i32),
+    /// Exited with any of these status codes
+    StatusCodes(Vec<i32>),
+    /// Exited with a status code in this range
+    StatusCodeRange(RangeInclusive<i32>),
+    /// Terminated by this signal, only on Unix
+    Signal(Signal),
+    /// Terminated by any signal, only on Unix
+    KilledBySignal,
+    /// Terminated by a signal and dumped core, only on Unix
+    CoreDumped,
+    SpawnFailed,
+    /// Doesn't match the pattern
+    Not(Box<ExitStatusPattern>),
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+impl ExitStatusPattern {
+    /// Whether `status` of an exited command matches this pattern.
+    ///
+    /// `spawned` is false if the command failed to spawn.
+    pub fn matches(&self, status: &io::Result<ExitStatus>, spawned: bool) -> bool {
+        let report = status
+            .as_ref()
+            .ok()
+            .map(|status| ExitStatusReport::from(*status));
+        let code = report.and_then(|report| report.code);
+
+        match self {
+            ExitStatusPattern::Success => report.is_some_and(|report| report.success),
+            ExitStatusPattern::Failed => !report.is_some_and(|report| report.success),
+            ExitStatusPattern::StatusCode(expected) => code == Some(*expected),
+            ExitStatusPattern::StatusCodes(codes) => code.is_some_and(|code| codes.contains(&code)),
+            ExitStatusPattern::StatusCodeRange(range) => {
+                code.is_some_and(|code| range.contains(&code))
+            }
+            ExitStatusPattern::Signal(signal) => {
+                report.is_some_and(|report| report.signal == Some(signal.0))
+            }
+            ExitStatusPattern::KilledBySignal => {
+                report.is_some_and(|report| report.signal.is_some())
+            }
+            ExitStatusPattern::CoreDumped => report.is_some_and(|report| report.core_dumped),
+            ExitStatusPattern::SpawnFailed => !spawned,
+            ExitStatusPattern::Not(pattern) => !pattern.matches(status, spawned),
+        }
+    }
+}
+
+impl Display for ExitStatusPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExitStatusPattern::Success => write!(f, "successfully"),
+            ExitStatusPattern::Failed => write!(f, "with failure"),
+            ExitStatusPattern::StatusCode(code) => write!(f, "with status code {}", code),
+            ExitStatusPattern::StatusCodes(codes) => write!(f, "with status code in {:?}", codes),
+            ExitStatusPattern::StatusCodeRange(range) => {
+                write!(f, "with status code in {:?}", range)
+            }
+            ExitStatusPattern::Signal(signal) => write!(f, "by signal {}", signal),
+            ExitStatusPattern::KilledBySignal => write!(f, "by a signal"),
+            ExitStatusPattern::CoreDumped => write!(f, "with core dumped"),
+            ExitStatusPattern::SpawnFailed => write!(f, "after failing to spawn"),
+            ExitStatusPattern::Not(pattern) => write!(f, "not {}", pattern),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidExitStatusPatternError(String);
+
+impl std::error::Error for InvalidExitStatusPatternError {}
+
+impl Display for InvalidExitStatusPatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid exit status pattern {:?}, expected like 1, 1,2, 2..=127, !0, SIGSEGV or SpawnFailed",
+            self.0
+        )
+    }
+}
+
+/// Parse the short syntax:
+///
+/// - `Success`, `Failed`, `KilledBySignal`, `CoreDumped` and `SpawnFailed`
+/// - a status code like `1`, codes like `1,2` or a range like `2..=127`
+/// - a signal like `SIGSEGV`
+/// - `!` followed by a pattern for negation
+impl std::str::FromStr for ExitStatusPattern {
+    type Err = InvalidExitStatusPatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || InvalidExitStatusPatternError(s.to_string());
+        let parse_code = |code: &str| code.trim().parse::<i32>().map_err(|_| err());
+        let pattern = s.trim();
+
+        if let Some(pattern) = pattern.strip_prefix('!') {
+            let pattern = pattern.parse().map_err(|_| err())?;
+            return Ok(ExitStatusPattern::Not(Box::new(pattern)));
+        }
+
+        match pattern {
+            "Success" => return Ok(ExitStatusPattern::Success),
+            "Failed" => return Ok(ExitStatusPattern::Failed),
+            "KilledBySignal" => return Ok(ExitStatusPattern::KilledBySignal),
+            "CoreDumped" => return Ok(ExitStatusPattern::CoreDumped),
+            "SpawnFailed" => return Ok(ExitStatusPattern::SpawnFailed),
+            _ => {}
+        }
+
+        if let Some((start, end)) = pattern.split_once("..=") {
+            return Ok(ExitStatusPattern::StatusCodeRange(
+                parse_code(start)?..=parse_code(end)?,
+            ));
+        }
+
+        if pattern.contains(',') {
+            let codes = pattern
+                .split(',')
+                .map(parse_code)
+                .collect::<Result<_, _>>()?;
+            return Ok(ExitStatusPattern::StatusCodes(codes));
+        }
+
+        if pattern.starts_with("SIG") {
+            return pattern
+                .parse()
+                .map(ExitStatusPattern::Signal)
+                .map_err(|_| err());
+        }
+
+        parse_code(pattern).map(ExitStatusPattern::StatusCode)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Default)]
 #[serde(from = "KillBehaviorInput")]
 pub enum KillBehavior {
+    #[default]
     None,
     WhenAnyExited,
     WhenAnyExitedWithStatus(ExitStatusPattern),
     // WhenLabeledExited,
 }
 
-impl Default for KillBehavior {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl Display for KillBehavior {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             KillBehavior::None => write!(f, "none"),
             KillBehavior::WhenAnyExited => write!(f, "kill other commands when any exited"),
             KillBehavior::WhenAnyExitedWithStatus(s) => {
-                let s: std::borrow::Cow<str> = match s {
-                    ExitStatusPattern::Success => "successfully".into(),
-                    ExitStatusPattern::Failed => "with failure".into(),
-                    ExitStatusPattern::StatusCode(code) => {
-                        format!("with status code {}", code).into()
-                    }
-                };
                 write!(f, "kill other commands when any exited {}", s)
             }
         }
@@ -48,6 +177,11 @@ impl std::str::FromStr for KillBehavior {
     type Err = serde_yaml::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `!0` would be parsed as a yaml tag
+        if let Ok(pattern) = s.parse() {
+            return Ok(KillBehavior::WhenAnyExitedWithStatus(pattern));
+        }
+
         let kill: KillBehaviorInput = serde_yaml::from_str(s)?;
 
         Ok(kill.into())
@@ -56,7 +190,7 @@ impl std::str::FromStr for KillBehavior {
 
 #[cfg(test)]
 mod tests {
-    use crate::{ExitStatusPattern, KillBehavior};
+    use crate::{ExitStatusPattern, KillBehavior, Signal};
 
     #[test]
     fn deserialize_kill_behavior() {
@@ -75,9 +209,127 @@ mod tests {
                 "123",
                 KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::StatusCode(123)),
             ),
+            (
+                "\"SIGSEGV\"",
+                KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::Signal(Signal::SIGSEGV)),
+            ),
+            (
+                "\"WhenAnyCoreDumped\"",
+                KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::CoreDumped),
+            ),
+            (
+                "\"WhenAnySpawnFailed\"",
+                KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::SpawnFailed),
+            ),
+            (
+                "[1, 2]",
+                KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::StatusCodes(vec![1, 2])),
+            ),
+            (
+                "\"2..=127\"",
+                KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::StatusCodeRange(2..=127)),
+            ),
+            (
+                r#"{"WhenAnyExitedWithStatus":{"Not":{"StatusCodes":[0,1]}}}"#,
+                KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::Not(Box::new(
+                    ExitStatusPattern::StatusCodes(vec![0, 1]),
+                ))),
+            ),
         ] {
             let res: KillBehavior = serde_json::from_str(input).unwrap();
             assert_eq!(res, val);
         }
+
+        assert!(serde_json::from_str::<KillBehavior>("\"WhenAnyKilled\"").is_err());
+    }
+
+    #[test]
+    fn parse_kill_behavior() {
+        for (input, val) in [
+            ("WhenAnyFailed", ExitStatusPattern::Failed),
+            ("1", ExitStatusPattern::StatusCode(1)),
+            ("2..=127", ExitStatusPattern::StatusCodeRange(2..=127)),
+            (
+                "!0,1",
+                ExitStatusPattern::Not(Box::new(ExitStatusPattern::StatusCodes(vec![0, 1]))),
+            ),
+        ] {
+            assert_eq!(
+                input.parse::<KillBehavior>().unwrap(),
+                KillBehavior::WhenAnyExitedWithStatus(val)
+            );
+        }
+        assert_eq!("None".parse::<KillBehavior>().unwrap(), KillBehavior::None);
+        assert!("1..".parse::<KillBehavior>().is_err());
+    }
+
+    #[test]
+    fn kill_behavior_round_trip() {
+        let kill = KillBehavior::WhenAnyExitedWithStatus(ExitStatusPattern::Not(Box::new(
+            ExitStatusPattern::StatusCodeRange(0..=1),
+        )));
+        let json = serde_json::to_string(&kill).unwrap();
+        assert_eq!(serde_json::from_str::<KillBehavior>(&json).unwrap(), kill);
+    }
+
+    #[test]
+    fn parse_exit_status_pattern() {
+        use ExitStatusPattern as P;
+
+        for (input, val) in [
+            ("Success", P::Success),
+            ("1", P::StatusCode(1)),
+            ("-1", P::StatusCode(-1)),
+            ("1, 2,3", P::StatusCodes(vec![1, 2, 3])),
+            ("2..=127", P::StatusCodeRange(2..=127)),
+            ("SIGTERM", P::Signal(Signal(15))),
+            ("KilledBySignal", P::KilledBySignal),
+            ("SpawnFailed", P::SpawnFailed),
+            ("!0", P::Not(Box::new(P::StatusCode(0)))),
+            ("!0,1", P::Not(Box::new(P::StatusCodes(vec![0, 1])))),
+        ] {
+            assert_eq!(input.parse::<P>(), Ok(val));
+        }
+
+        for input in ["", "!", "1..2", "a,b", "SIGFOO", "success"] {
+            assert!(input.parse::<P>().is_err(), "{:?}", input);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn match_exit_status() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::ExitStatus;
+        use ExitStatusPattern as P;
+
+        let code_1 = Ok(ExitStatus::from_raw(1 << 8));
+        let code_2 = Ok(ExitStatus::from_raw(2 << 8));
+        // SIGSEGV with the core dump flag
+        let segfault = Ok(ExitStatus::from_raw(11 | 0x80));
+        let spawn_failed = Err(std::io::ErrorKind::NotFound.into());
+
+        assert!(P::StatusCode(1).matches(&code_1, true));
+        assert!(P::Failed.matches(&segfault, true));
+        assert!(P::Signal(Signal::SIGSEGV).matches(&segfault, true));
+        assert!(!P::Signal(Signal::SIGSEGV).matches(&code_1, true));
+        assert!(P::KilledBySignal.matches(&segfault, true));
+        assert!(P::CoreDumped.matches(&segfault, true));
+        assert!(P::Failed.matches(&spawn_failed, false));
+        assert!(!P::StatusCode(1).matches(&spawn_failed, false));
+
+        assert!(P::StatusCodes(vec![1, 3]).matches(&code_1, true));
+        assert!(!P::StatusCodes(vec![1, 3]).matches(&code_2, true));
+        assert!(P::StatusCodeRange(2..=127).matches(&code_2, true));
+        assert!(!P::StatusCodeRange(2..=127).matches(&code_1, true));
+        assert!(!P::StatusCodeRange(2..=127).matches(&segfault, true));
+
+        assert!(P::SpawnFailed.matches(&spawn_failed, false));
+        assert!(!P::SpawnFailed.matches(&code_1, true));
+
+        let not_0_1 = P::Not(Box::new(P::StatusCodes(vec![0, 1])));
+        assert!(!not_0_1.matches(&code_1, true));
+        assert!(not_0_1.matches(&code_2, true));
+        assert!(not_0_1.matches(&segfault, true));
     }
 }
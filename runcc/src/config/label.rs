@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+/// How to truncate labels longer than `max_label_length`
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelTruncate {
+    /// Keep the start, like `cargo run -p ve...`
+    #[default]
+    End,
+    /// Keep the start and the end, like `cargo r...e-name`
+    Middle,
+    /// Keep the last segment separated by spaces or slashes,
+    /// like `very-long-crate-name` of `cargo run -p very-long-crate-name`.
+    ///
+    /// If the segment is still too long, it is truncated at the end.
+    #[serde(alias = "last-segment")]
+    LastSegment,
+}
+
+impl std::str::FromStr for LabelTruncate {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// How to align labels shorter than `max_label_length`
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelAlign {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+impl std::str::FromStr for LabelAlign {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// How labels are decorated in the default prefix and messages of runcc
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelStyle {
+    /// `[api]`
+    #[default]
+    Brackets,
+    /// `api |`
+    Pipe,
+    /// `api`
+    None,
+}
+
+impl LabelStyle {
+    /// Decorate `label` in this style
+    pub fn wrap(self, label: &str) -> String {
+        match self {
+            LabelStyle::Brackets => format!("[{}]", label),
+            LabelStyle::Pipe => format!("{} |", label),
+            LabelStyle::None => label.to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for LabelStyle {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
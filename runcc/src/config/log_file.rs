@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFileMode {
+    /// Append to existing log files
+    #[default]
+    Append,
+    /// Clear existing log files when runcc starts
+    Truncate,
+}
+
+/// How output of commands is written to log files
+#[non_exhaustive]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct LogFileOptions {
+    pub mode: LogFileMode,
+    /// Remove ANSI escape sequences like colors
+    pub strip_ansi: bool,
+    /// Rotate a log file when it would exceed this size in bytes.
+    ///
+    /// `app.log` is renamed to `app.log.1`, `app.log.1` to `app.log.2` and so on.
+    pub max_size: Option<u64>,
+    /// Max number of rotated files to keep, not including the current one
+    pub max_files: usize,
+}
+
+impl Default for LogFileOptions {
+    fn default() -> Self {
+        Self {
+            mode: LogFileMode::default(),
+            strip_ansi: true,
+            max_size: None,
+            max_files: 5,
+        }
+    }
+}
+
+/// Log file of a command in `log_dir`, named after the label.
+///
+/// Characters which may not be valid in file names are replaced with `_`.
+pub fn log_file_in_dir(log_dir: &str, label: &str) -> PathBuf {
+    let name: String = label
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "-_. ".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    PathBuf::from(log_dir).join(format!("{}.log", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{log_file_in_dir, LogFileMode, LogFileOptions};
+    use std::path::Path;
+
+    #[test]
+    fn log_file_name() {
+        assert_eq!(
+            log_file_in_dir("logs", "api"),
+            Path::new("logs").join("api.log")
+        );
+        assert_eq!(
+            log_file_in_dir("logs", "cargo run -p a/b"),
+            Path::new("logs").join("cargo run -p a_b.log")
+        );
+    }
+
+    #[test]
+    fn deserialize_log_file_options() {
+        let options: LogFileOptions =
+            serde_yaml::from_str("mode: truncate\nmax_size: 1024").unwrap();
+        assert_eq!(options.mode, LogFileMode::Truncate);
+        assert_eq!(options.max_size, Some(1024));
+        assert!(options.strip_ansi);
+    }
+}
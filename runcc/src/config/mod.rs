@@ -1,11 +1,29 @@
+mod color;
 mod command;
+mod encoding;
 mod input;
 mod kill;
+mod label;
+mod log_file;
+mod output;
+mod placeholder;
+mod report;
 mod run;
+mod signal;
+mod timestamp;
 
+pub use color::*;
 pub use command::*;
+pub use encoding::*;
 pub use input::*;
 pub use kill::*;
+pub use label::*;
+pub use log_file::*;
+pub use output::*;
+pub use placeholder::*;
+pub use report::*;
 pub use run::*;
+pub use signal::*;
+pub use timestamp::*;
 
 pub mod read;
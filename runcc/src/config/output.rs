@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    /// Print lines as soon as they are read
+    #[default]
+    Interleaved,
+    /// Buffer output of each command and print it as one block when the command exits
+    Grouped,
+    /// Print one JSON object per line for each event, like output lines and exits of commands
+    Jsonl,
+}
+
+impl std::str::FromStr for OutputMode {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// Header and footer of output blocks in grouped output mode
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupStyle {
+    /// `github` in GitHub Actions, `gitlab` in GitLab CI, otherwise `plain`
+    #[default]
+    Auto,
+    Plain,
+    /// `::group::` and `::endgroup::` of GitHub Actions
+    Github,
+    /// Collapsible sections of GitLab CI
+    Gitlab,
+}
+
+impl GroupStyle {
+    /// Resolve `Auto` by env vars of CI
+    pub fn resolve(self) -> Self {
+        match self {
+            GroupStyle::Auto => {
+                let is_set = |name| std::env::var_os(name).is_some_and(|v| !v.is_empty());
+                if is_set("GITHUB_ACTIONS") {
+                    GroupStyle::Github
+                } else if is_set("GITLAB_CI") {
+                    GroupStyle::Gitlab
+                } else {
+                    GroupStyle::Plain
+                }
+            }
+            style => style,
+        }
+    }
+}
+
+impl std::str::FromStr for GroupStyle {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// Rules to filter and highlight output lines of a command in logs.
+///
+/// Regexes are matched against lines without ANSI escape sequences.
+/// Lines hidden by these rules are still written to log files.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct OutputRules {
+    /// Hide lines matching any of these regexes, like health checks
+    pub hide: Option<Vec<String>>,
+    /// Only show lines matching any of these regexes
+    pub only: Option<Vec<String>>,
+    /// Highlight matches of regexes in colors
+    pub highlight: Option<Vec<HighlightRule>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct HighlightRule {
+    pub regex: String,
+    /// Defaults to yellow
+    pub color: Option<super::Color>,
+}
+
+/// Where output of a stream of a command is written
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputVisibility {
+    /// Print to the terminal, and write to the log file if any
+    #[default]
+    Show,
+    /// Discard output, which is still read so that the command doesn't block
+    Hide,
+    /// Only write to the log file, which defaults to `{label}.log` in `log_dir`.
+    /// Either `log_file` or `log_dir` must be set.
+    File,
+}
+
+impl std::str::FromStr for OutputVisibility {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
@@ -0,0 +1,255 @@
+use std::borrow::Cow;
+
+/// Quote `s` as one argument for the shell which runs command scripts,
+/// `sh` on unix and `cmd` on windows.
+///
+/// ```
+/// # use runcc::shell_quote;
+/// assert_eq!(shell_quote("foo"), "foo");
+/// # #[cfg(not(windows))]
+/// assert_eq!(shell_quote("it's ok"), r"'it'\''s ok'");
+/// # #[cfg(windows)]
+/// assert_eq!(shell_quote("it's ok"), r#"^"it's ok^""#);
+/// ```
+pub fn shell_quote(s: &str) -> Cow<'_, str> {
+    if cfg!(target_os = "windows") {
+        cmd_quote(s)
+    } else {
+        sh_quote(s)
+    }
+}
+
+fn sh_quote(s: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=@,+%".contains(c);
+
+    if !s.is_empty() && s.chars().all(is_safe) {
+        return s.into();
+    }
+
+    format!("'{}'", s.replace('\'', r"'\''")).into()
+}
+
+/// Quote `s` for programs parsing arguments like `CommandLineToArgvW`,
+/// and then escape all special characters of `cmd` with `^`,
+/// so that `cmd` doesn't expand env vars or run other commands in `s`.
+fn cmd_quote(s: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=@,+".contains(c);
+
+    if !s.is_empty() && s.chars().all(is_safe) {
+        return s.into();
+    }
+
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in s.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // backslashes before a quote and the quote itself are escaped
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            quoted.push(c);
+        }
+    }
+    // backslashes before the closing quote are escaped
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+
+    let mut res = String::with_capacity(quoted.len() * 2);
+    for c in quoted.chars() {
+        if "()%!^\"<>&|".contains(c) {
+            res.push('^');
+        }
+        res.push(c);
+    }
+
+    res.into()
+}
+
+/// Keep `s` as is, for replacing placeholders without quoting
+pub(crate) fn no_quote(s: &str) -> Cow<'_, str> {
+    s.into()
+}
+
+/// Replace placeholders in `s` with `args`.
+///
+/// - `{1}`, `{2}`, ... is replaced with the n-th argument
+/// - `{@}` is replaced with all arguments, quoted one by one and joined with spaces
+/// - `{*}` is replaced with all arguments joined with spaces, quoted as a whole
+///
+/// Each value is quoted by `quote`.
+/// Placeholders without arguments are replaced with nothing.
+pub fn replace_placeholders<'a, Q>(s: &'a str, args: &[String], quote: Q) -> Cow<'a, str>
+where
+    Q: Fn(&str) -> Cow<str>,
+{
+    if !s.contains('{') {
+        return s.into();
+    }
+
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(start) = rest.find('{') {
+        res.push_str(&rest[..start]);
+        let after = &rest[(start + 1)..];
+
+        let end = match after.find('}') {
+            Some(end) => end,
+            None => {
+                res.push_str(&rest[start..]);
+                rest = "";
+                break;
+            }
+        };
+
+        let placeholder = &after[..end];
+        match placeholder {
+            "@" => {
+                let quoted: Vec<_> = args.iter().map(|arg| quote(arg)).collect();
+                res.push_str(&quoted.join(" "));
+            }
+            "*" => {
+                if !args.is_empty() {
+                    res.push_str(&quote(&args.join(" ")));
+                }
+            }
+            n if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) && !n.starts_with('0') => {
+                let arg = n.parse::<usize>().ok().and_then(|n| args.get(n - 1));
+                if let Some(arg) = arg {
+                    res.push_str(&quote(arg));
+                }
+            }
+            _ => {
+                res.push('{');
+                rest = after;
+                continue;
+            }
+        }
+
+        rest = &after[(end + 1)..];
+    }
+
+    res.push_str(rest);
+
+    res.into()
+}
+
+/// Replace placeholders in program arguments with `args`.
+///
+/// An argument which is exactly `{@}` expands to all `args`.
+/// Other placeholders are replaced without quoting.
+pub fn replace_placeholders_in_args(program_args: Vec<String>, args: &[String]) -> Vec<String> {
+    let mut res = Vec::with_capacity(program_args.len());
+
+    for arg in program_args {
+        if arg == "{@}" {
+            res.extend(args.iter().cloned());
+        } else if let Cow::Owned(arg) = replace_placeholders(&arg, args, no_quote) {
+            res.push(arg);
+        } else {
+            res.push(arg);
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cmd_quote, no_quote, replace_placeholders, replace_placeholders_in_args};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn replace_placeholders_raw() {
+        let passthrough = args(&["-p", "foo bar"]);
+        for (s, res) in [
+            ("cargo test", "cargo test"),
+            ("cargo test {1}", "cargo test -p"),
+            ("cargo test {1} {2} {3}", "cargo test -p foo bar "),
+            ("cargo test {@}", "cargo test -p foo bar"),
+            ("cargo test {*}", "cargo test -p foo bar"),
+            ("{0} {01} {} {a} {1", "{0} {01} {} {a} {1"),
+            ("awk '{print $1}' {1}", "awk '{print $1}' -p"),
+        ] {
+            assert_eq!(replace_placeholders(s, &passthrough, no_quote), res);
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn replace_placeholders_shell_quoted() {
+        let passthrough = args(&["-p", "foo bar", "it's"]);
+        for (s, res) in [
+            ("cargo test {1}", "cargo test -p"),
+            ("cargo test {2}", "cargo test 'foo bar'"),
+            ("cargo test {4}", "cargo test "),
+            ("cargo test {@}", r"cargo test -p 'foo bar' 'it'\''s'"),
+            ("echo {*}", r"echo '-p foo bar it'\''s'"),
+        ] {
+            assert_eq!(
+                replace_placeholders(s, &passthrough, super::shell_quote),
+                res
+            );
+        }
+    }
+
+    #[test]
+    fn quote_for_cmd() {
+        for (s, res) in [
+            ("foo", "foo"),
+            ("", r#"^"^""#),
+            ("foo bar", r#"^"foo bar^""#),
+            ("%PATH%", r#"^"^%PATH^%^""#),
+            (r#"a"b"#, r#"^"a\^"b^""#),
+            (r"C:\dir\", r#"^"C:\dir\\^""#),
+            (r#"a\"b"#, r#"^"a\\\^"b^""#),
+            ("a & b | c > d", r#"^"a ^& b ^| c ^> d^""#),
+            ("(!^)", r#"^"^(^!^^^)^""#),
+        ] {
+            assert_eq!(cmd_quote(s), res, "{:?}", s);
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn replace_placeholders_shell_quoted() {
+        let passthrough = args(&["-p", "foo bar", "%USERNAME%"]);
+        for (s, res) in [
+            ("cargo test {1}", "cargo test -p"),
+            ("cargo test {2}", r#"cargo test ^"foo bar^""#),
+            ("echo {3}", r#"echo ^"^%USERNAME^%^""#),
+            (
+                "cargo test {@}",
+                r#"cargo test -p ^"foo bar^" ^"^%USERNAME^%^""#,
+            ),
+        ] {
+            assert_eq!(
+                replace_placeholders(s, &passthrough, super::shell_quote),
+                res
+            );
+        }
+    }
+
+    #[test]
+    fn replace_placeholders_args() {
+        assert_eq!(
+            replace_placeholders_in_args(
+                args(&["test", "{@}", "--", "{2}", "x{*}"]),
+                &args(&["-p", "foo bar"])
+            ),
+            args(&["test", "-p", "foo bar", "--", "foo bar", "x-p foo bar"])
+        );
+    }
+}
@@ -142,6 +142,12 @@ pub enum FindConfigError {
         file: String,
         extension: Option<String>,
     },
+    /// Config files extend or include each other in a loop.
+    ///
+    /// `chain` starts from the first config file in the loop and ends with it again.
+    IncludeLoop {
+        chain: Vec<String>,
+    },
 }
 
 impl error::Error for FindConfigError {
@@ -172,6 +178,13 @@ impl Display for FindConfigError {
                     file
                 )
             }
+            FindConfigError::IncludeLoop { chain } => {
+                write!(
+                    f,
+                    "Config files extend or include each other in a loop: {}",
+                    chain.join(" -> ")
+                )
+            }
         }
     }
 }
@@ -51,18 +51,18 @@ pub struct ConfigFileData<T> {
     pub data: T,
 }
 
-pub fn find_config_file_in_dir<T: DeserializeOwned>(
+/// Find `{name}.{json, yml, yaml, ron, toml}` in `dir_path`.
+///
+/// Returns `Ok(None)` if none of these files exist.
+pub fn find_optional_config_file_in_dir<T: DeserializeOwned>(
     dir_path: &Path,
-    app_name: &str,
-) -> Result<ConfigFileData<T>, FindConfigError> {
-    let mut patterns = Vec::with_capacity(EXTENSIONS.len() + 1);
+    name: &str,
+) -> Result<Option<ConfigFileData<T>>, FindConfigError> {
     for (format, ext) in EXTENSIONS {
-        let filename = format!("{}{}", app_name, ext);
-        let file = dir_path.join(&filename);
-        patterns.push(filename);
+        let file = dir_path.join(format!("{}{}", name, ext));
 
         match read_config_from_file_and_format(&file, format) {
-            Ok(conf) => return Ok(conf),
+            Ok(conf) => return Ok(Some(conf)),
             Err(err) => match err {
                 ReadConfigError::OpenFileError { error, .. }
                     if error.kind() == io::ErrorKind::NotFound =>
@@ -74,6 +74,22 @@ pub fn find_config_file_in_dir<T: DeserializeOwned>(
         };
     }
 
+    Ok(None)
+}
+
+pub fn find_config_file_in_dir<T: DeserializeOwned>(
+    dir_path: &Path,
+    app_name: &str,
+) -> Result<ConfigFileData<T>, FindConfigError> {
+    if let Some(conf) = find_optional_config_file_in_dir(dir_path, app_name)? {
+        return Ok(conf);
+    }
+
+    let mut patterns: Vec<_> = EXTENSIONS
+        .iter()
+        .map(|(_, ext)| format!("{}{}", app_name, ext))
+        .collect();
+
     match read_config_from_cargo_toml(&dir_path.join("Cargo.toml"), app_name) {
         Ok(Some(conf)) => return Ok(conf),
         Ok(None) => {
@@ -172,7 +188,7 @@ pub fn read_config_from_cargo_toml<T: DeserializeOwned>(
                 .and_then(|v| remove_toml_key_path(v, ["metadata", app_name]));
 
             let v = if let Some(pkg) = pkg {
-                if let Some(_) = wsp {
+                if wsp.is_some() {
                     return Err(ConfigDeserializeError {
                         filename: filename.to_string(),
                         format,
@@ -192,32 +208,28 @@ pub fn read_config_from_cargo_toml<T: DeserializeOwned>(
                 }
             };
 
-            let data: T = v.try_into().or_else(|err| {
-                Err(ConfigDeserializeError {
-                    filename: filename.to_string(),
-                    format,
-                    kind: ConfigDeserializeErrorKind::CargoMetadataError(
-                        CargoMetadataError::InvalidDataStructure(err),
-                    ),
-                })
+            let data: T = v.try_into().map_err(|err| ConfigDeserializeError {
+                filename: filename.to_string(),
+                format,
+                kind: ConfigDeserializeErrorKind::CargoMetadataError(
+                    CargoMetadataError::InvalidDataStructure(err),
+                ),
             })?;
 
-            return Ok(Some(ConfigFileData {
+            Ok(Some(ConfigFileData {
                 filename: "Cargo.toml".to_string(),
                 format: ConfigFormat::CargoMetadata,
                 data,
-            }));
+            }))
         }
-        _ => {
-            return Err(ConfigDeserializeError {
-                filename: filename.to_string(),
-                format,
-                kind: ConfigDeserializeErrorKind::CargoMetadataError(
-                    CargoMetadataError::CargoTomlIsNotTable,
-                ),
-            }
-            .into())
+        _ => Err(ConfigDeserializeError {
+            filename: filename.to_string(),
+            format,
+            kind: ConfigDeserializeErrorKind::CargoMetadataError(
+                CargoMetadataError::CargoTomlIsNotTable,
+            ),
         }
+        .into()),
     }
 }
 
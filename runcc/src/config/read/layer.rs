@@ -0,0 +1,244 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    error::*, find_config_file, find_optional_config_file_in_dir, ConfigFileData, ConfigFormat,
+};
+use crate::RunConfigInput;
+
+#[non_exhaustive]
+pub struct LayeredConfigFileData<T> {
+    pub filename: String,
+    pub format: ConfigFormat,
+    /// All config files merged into `data`, from the lowest precedence to the highest.
+    pub layers: Vec<String>,
+    pub data: T,
+}
+
+struct LayerResolver<'a> {
+    app_name: &'a str,
+    /// (canonicalized path, display name) of config files being resolved
+    chain: Vec<(PathBuf, String)>,
+    layers: Vec<String>,
+    inputs: Vec<RunConfigInput>,
+}
+
+/// The path of the config file found by [`find_config_file`] with `searched_path`
+fn config_file_path(searched_path: &Path, filename: &str, format: ConfigFormat) -> PathBuf {
+    match format {
+        ConfigFormat::CargoMetadata
+            if searched_path.file_name().unwrap_or_default() != "Cargo.toml" =>
+        {
+            searched_path.join("Cargo.toml")
+        }
+        ConfigFormat::CargoMetadata => searched_path.to_path_buf(),
+        _ => PathBuf::from(filename),
+    }
+}
+
+impl<'a> LayerResolver<'a> {
+    fn resolve_path(&mut self, path: &Path) -> Result<(), FindConfigError> {
+        let data = find_config_file(Some(&path.to_string_lossy()), self.app_name)?;
+        let path = config_file_path(path, &data.filename, data.format);
+
+        self.resolve(data.data, path)
+    }
+
+    fn resolve(&mut self, mut input: RunConfigInput, path: PathBuf) -> Result<(), FindConfigError> {
+        let name = path.to_string_lossy().into_owned();
+        let key = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+
+        if let Some(i) = self.chain.iter().position(|(k, _)| *k == key) {
+            let mut chain: Vec<_> = self.chain[i..]
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect();
+            chain.push(name);
+            return Err(FindConfigError::IncludeLoop { chain });
+        }
+
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let extends = input.extends.take().map(|extends| dir.join(extends));
+        let include: Vec<_> = input
+            .include
+            .take()
+            .into_iter()
+            .flatten()
+            .map(|include| dir.join(include))
+            .collect();
+        input.env_file = input.env_file.map(|env_file| {
+            env_file
+                .into_iter()
+                .map(|file| dir.join(file).to_string_lossy().into_owned())
+                .collect()
+        });
+
+        self.chain.push((key, name.clone()));
+
+        if let Some(extends) = extends {
+            self.resolve_path(&extends)?;
+        }
+
+        self.layers.push(name);
+        self.inputs.push(input);
+
+        for include in include {
+            self.resolve_path(&include)?;
+        }
+
+        self.chain.pop();
+
+        Ok(())
+    }
+}
+
+/// Find a config file like [`find_config_file`] and resolve it into layers.
+///
+/// - `extends` is merged before the config file
+/// - `include` are merged after the config file, in order
+/// - `{app_name}.local.{json, yml, yaml, ron, toml}` in the same directory
+///   is merged at last if it exists
+///
+/// See [`RunConfigInput::merge_layers`] for how layers are merged.
+pub fn find_layered_config_file(
+    file_or_dir_path: Option<&str>,
+    app_name: &str,
+) -> Result<LayeredConfigFileData<RunConfigInput>, FindConfigError> {
+    let searched_path = Path::new(file_or_dir_path.unwrap_or(""));
+    let ConfigFileData {
+        filename,
+        format,
+        data,
+    } = find_config_file(file_or_dir_path, app_name)?;
+
+    let path = config_file_path(searched_path, &filename, format);
+
+    let mut resolver = LayerResolver {
+        app_name,
+        chain: vec![],
+        layers: vec![],
+        inputs: vec![],
+    };
+
+    resolver.resolve(data, path.clone())?;
+
+    let local_name = format!("{}.local", app_name);
+    let is_local = path.file_name().is_some_and(|name| {
+        name.to_string_lossy()
+            .starts_with(&format!("{}.", local_name))
+    });
+
+    if !is_local {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        if let Some(local) = find_optional_config_file_in_dir(dir, &local_name)? {
+            let local_path = PathBuf::from(&local.filename);
+            resolver.resolve(local.data, local_path)?;
+        }
+    }
+
+    let LayerResolver { layers, inputs, .. } = resolver;
+
+    Ok(LayeredConfigFileData {
+        filename,
+        format,
+        layers,
+        data: RunConfigInput::merge_layers(inputs).expect("there is at least one layer"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::find_layered_config_file;
+    use crate::read::error::FindConfigError;
+
+    #[test]
+    fn detect_include_loop() {
+        let dir =
+            std::env::temp_dir().join(format!("runcc-test-include-loop-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+
+        fs::write(
+            dir.join("runcc.yml"),
+            "extends: nested/base.yml\ncommands: [echo 1]",
+        )
+        .unwrap();
+        fs::write(dir.join("nested/base.yml"), "include: [../shared.yml]").unwrap();
+        fs::write(dir.join("shared.yml"), "extends: runcc.yml").unwrap();
+
+        let res = find_layered_config_file(Some(&dir.to_string_lossy()), "runcc");
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        match res {
+            Err(FindConfigError::IncludeLoop { chain }) => {
+                assert_eq!(chain.len(), 4);
+                assert!(chain[0].ends_with("runcc.yml"));
+                assert!(chain[1].ends_with("base.yml"));
+                assert!(chain[2].ends_with("shared.yml"));
+                assert!(chain[3].ends_with("runcc.yml"));
+            }
+            _ => panic!("include loop should be detected"),
+        }
+    }
+
+    #[test]
+    fn merge_local_config() {
+        let dir = std::env::temp_dir().join(format!("runcc-test-local-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        fs::write(
+            dir.join("base.yml"),
+            "commands: { a: [echo, a], b: [echo, b] }",
+        )
+        .unwrap();
+        fs::write(dir.join("runcc.yml"), "extends: base.yml\nenvs: { A: a }").unwrap();
+        fs::write(dir.join("runcc.local.yaml"), "commands: { b: false }").unwrap();
+
+        let res = find_layered_config_file(Some(&dir.to_string_lossy()), "runcc");
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let res = res.unwrap_or_else(|err| panic!("config should be resolved: {}", err));
+        assert_eq!(res.layers.len(), 3);
+        assert!(res.layers[2].ends_with("runcc.local.yaml"));
+
+        let config: crate::RunConfig = res.data.into();
+        assert_eq!(config.commands.len(), 1);
+        assert_eq!(config.commands[0].label_or_default(), "a");
+        assert_eq!(config.envs.unwrap()["A"], "a");
+    }
+
+    #[test]
+    fn resolve_env_file_relative_to_config_file() {
+        let dir = std::env::temp_dir().join(format!("runcc-test-env-file-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+
+        fs::write(
+            dir.join("nested/base.yml"),
+            "env_file: [.env]\ncommands: [echo 1]",
+        )
+        .unwrap();
+        fs::write(dir.join("nested/.env"), "A=nested\nB=nested").unwrap();
+        fs::write(
+            dir.join("runcc.yml"),
+            "extends: nested/base.yml\nenv_file: [.env]",
+        )
+        .unwrap();
+        fs::write(dir.join(".env"), "B=root").unwrap();
+
+        let res = find_layered_config_file(Some(&dir.to_string_lossy()), "runcc").map(|res| {
+            let mut config: crate::RunConfig = res.data.into();
+            config.load_env_files().map(|()| config.envs)
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let envs = res.unwrap().unwrap().unwrap();
+        assert_eq!(envs["A"], "nested");
+        assert_eq!(envs["B"], "root");
+    }
+}
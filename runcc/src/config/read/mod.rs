@@ -1,4 +1,6 @@
 pub mod error;
 
 mod format;
+mod layer;
 pub use format::*;
+pub use layer::*;
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::PathBuf;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    /// JUnit XML, where each command is a test case
+    Junit,
+    /// [`CommandSystemReport`](crate::run::CommandSystemReport) as JSON
+    Json,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
+
+/// A report file written when all commands exit.
+///
+/// In config files and cli, it's written as `junit:path.xml` or `json:path.json`.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct ReportFile {
+    pub format: ReportFormat,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidReportFileError(String);
+
+impl std::error::Error for InvalidReportFileError {}
+
+impl Display for InvalidReportFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid report {:?}, expected junit:path.xml or json:path.json",
+            self.0
+        )
+    }
+}
+
+impl std::str::FromStr for ReportFile {
+    type Err = InvalidReportFileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || InvalidReportFileError(s.to_string());
+
+        let (format, path) = s.split_once(':').ok_or_else(err)?;
+        if path.is_empty() {
+            return Err(err());
+        }
+
+        Ok(Self {
+            format: format.parse().map_err(|_| err())?,
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+impl std::convert::TryFrom<String> for ReportFile {
+    type Error = InvalidReportFileError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ReportFile> for String {
+    fn from(report: ReportFile) -> Self {
+        let format = match report.format {
+            ReportFormat::Junit => "junit",
+            ReportFormat::Json => "json",
+        };
+        format!("{}:{}", format, report.path.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{ReportFile, ReportFormat};
+
+    #[test]
+    fn parse_report_file() {
+        assert_eq!(
+            "junit:target/runcc.xml".parse::<ReportFile>(),
+            Ok(ReportFile {
+                format: ReportFormat::Junit,
+                path: PathBuf::from("target/runcc.xml"),
+            })
+        );
+        assert_eq!(
+            "json:C:\\runcc.json".parse::<ReportFile>().unwrap().path,
+            PathBuf::from("C:\\runcc.json")
+        );
+        assert!("junit".parse::<ReportFile>().is_err());
+        assert!("junit:".parse::<ReportFile>().is_err());
+        assert!("xml:runcc.xml".parse::<ReportFile>().is_err());
+    }
+}
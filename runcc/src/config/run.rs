@@ -1,14 +1,153 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, io, path::Path};
 
-use super::{CommandConfig, KillBehavior};
+use super::{
+    ColorChoice, CommandConfig, GroupStyle, KillBehavior, LabelAlign, LabelStyle, LabelTruncate,
+    LogFileOptions, OutputMode, ReportFile, TimestampMode,
+};
 
 #[non_exhaustive]
 #[derive(Deserialize, Serialize, Debug)]
 pub struct RunConfig {
     pub commands: Vec<CommandConfig>,
+    /// Max display width of labels in terminals
     pub max_label_length: usize,
+    /// How to truncate labels longer than `max_label_length`
+    #[serde(default)]
+    pub label_truncate: LabelTruncate,
+    /// How to align labels shorter than `max_label_length`
+    #[serde(default)]
+    pub label_align: LabelAlign,
+    /// How labels are decorated in the default prefix, like `[api]` or `api |`
+    #[serde(default)]
+    pub label_style: LabelStyle,
+    /// Show the index of the command before the label, like `[2:api]`
+    #[serde(default)]
+    pub show_index: bool,
     pub envs: Option<HashMap<String, String>>,
+    /// Dotenv files for all commands.
+    ///
+    /// See [`RunConfig::load_env_files`].
+    #[serde(default)]
+    pub env_file: Option<Vec<String>>,
+    /// Don't inherit any env vars from runcc process.
+    ///
+    /// See [`CommandConfig::inherit_env_isolation`] for how it's merged with commands.
+    #[serde(default)]
+    pub env_clear: Option<bool>,
+    /// Env vars not to inherit from runcc process.
+    #[serde(default)]
+    pub env_remove: Option<Vec<String>>,
+    /// Only inherit these env vars from runcc process.
+    #[serde(default)]
+    pub env_inherit: Option<Vec<String>>,
     #[serde(default)]
     pub kill: KillBehavior,
+    /// Whether runcc and commands should colorize output.
+    #[serde(default)]
+    pub color: ColorChoice,
+    /// Env vars set for all commands before `envs`.
+    ///
+    /// Defaults to [`ColorChoice::child_env_defaults`] of `color`.
+    /// Set to an empty map to disable.
+    #[serde(default)]
+    pub child_env_defaults: Option<HashMap<String, String>>,
+    /// Template of the prefix of each output line, like `[{label}]`.
+    ///
+    /// `none` means no prefix.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Add timestamps to output lines, with `{time}` in `prefix`
+    #[serde(default)]
+    pub timestamps: Option<TimestampMode>,
+    /// Format of timestamps, like `%H:%M:%S%.3f`
+    #[serde(default)]
+    pub timestamp_format: Option<String>,
+    /// Directory to write log files of commands without `log_file`
+    #[serde(default)]
+    pub log_dir: Option<String>,
+    #[serde(default)]
+    pub log_file_options: LogFileOptions,
+    #[serde(default)]
+    pub output: OutputMode,
+    /// Style of output blocks in grouped output mode
+    #[serde(default)]
+    pub group_style: GroupStyle,
+    /// Show lines being written like progress bars in place, in interleaved output mode
+    #[serde(default)]
+    pub show_progress: bool,
+    /// Flush a line which is not ended, like a prompt,
+    /// after no output is read for this duration in milliseconds.
+    ///
+    /// Defaults to 500. `0` disables it.
+    #[serde(default)]
+    pub partial_line_timeout: Option<u64>,
+    /// Only show output lines matching this regex, for all commands.
+    ///
+    /// Lines are still written to log files.
+    #[serde(default)]
+    pub grep: Option<String>,
+    /// Print failed commands with their last lines of output when all commands exit.
+    ///
+    /// Defaults to 10 lines. `0` disables it.
+    #[serde(default)]
+    pub tail_on_failure: Option<usize>,
+    /// Print a table of all commands with their statuses and durations when all commands exit.
+    ///
+    /// Defaults to true.
+    #[serde(default)]
+    pub show_summary: Option<bool>,
+    /// Report files written when all commands exit, like `junit:runcc.xml`
+    #[serde(default)]
+    pub reports: Vec<ReportFile>,
+}
+
+impl RunConfig {
+    /// Read envs from `env_file` into `envs`.
+    ///
+    /// Envs from env files have lower precedence than `envs`,
+    /// and later env files have higher precedence.
+    /// Env files which don't exist are ignored.
+    ///
+    /// `env_file` is ignored by [`spawn_from_run_config_with_plugin`](crate::run::spawn_from_run_config_with_plugin),
+    /// so this should be called before it.
+    pub fn load_env_files(&mut self) -> io::Result<()> {
+        let env_file = match self.env_file.take() {
+            Some(env_file) => env_file,
+            None => return Ok(()),
+        };
+
+        let mut envs = HashMap::new();
+        for file in env_file {
+            if let Some(file_envs) = crate::read_env_file(Path::new(&file))? {
+                envs.extend(file_envs);
+            }
+        }
+
+        if let Some(own_envs) = self.envs.take() {
+            envs.extend(own_envs);
+        }
+
+        if !envs.is_empty() {
+            self.envs = Some(envs);
+        }
+
+        Ok(())
+    }
+
+    /// The first command whose `stdout` or `stderr` is only written to the log file,
+    /// but which has neither `log_file` nor `log_dir` set.
+    ///
+    /// Such a config is invalid. It's not checked by
+    /// [`spawn_from_run_config_with_plugin`](crate::run::spawn_from_run_config_with_plugin),
+    /// so this should be checked before it.
+    pub fn command_without_log_file(&self) -> Option<&CommandConfig> {
+        if self.log_dir.is_some() {
+            return None;
+        }
+
+        self.commands
+            .iter()
+            .find(|cmd| cmd.writes_to_log_file_only() && cmd.log_file.is_none())
+    }
 }
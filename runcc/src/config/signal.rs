@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Names of signals whose numbers are the same on Linux, macOS and BSDs
+const SIGNAL_NAMES: [(i32, &str); 12] = [
+    (1, "SIGHUP"),
+    (2, "SIGINT"),
+    (3, "SIGQUIT"),
+    (4, "SIGILL"),
+    (5, "SIGTRAP"),
+    (6, "SIGABRT"),
+    (8, "SIGFPE"),
+    (9, "SIGKILL"),
+    (11, "SIGSEGV"),
+    (13, "SIGPIPE"),
+    (14, "SIGALRM"),
+    (15, "SIGTERM"),
+];
+
+/// A Unix signal which terminated a command.
+///
+/// In config files, it can be a name like `SIGSEGV` or a number like `11`.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "SignalInput", into = "SignalInput")]
+pub struct Signal(pub i32);
+
+impl Signal {
+    pub const SIGSEGV: Signal = Signal(11);
+
+    /// Name like `SIGSEGV`, `None` if the signal is not portable
+    pub fn name(self) -> Option<&'static str> {
+        SIGNAL_NAMES
+            .iter()
+            .find(|(signal, _)| *signal == self.0)
+            .map(|(_, name)| *name)
+    }
+}
+
+/// Like `11 (SIGSEGV)`
+impl Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{} ({})", self.0, name),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidSignalError(String);
+
+impl std::error::Error for InvalidSignalError {}
+
+impl Display for InvalidSignalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid signal {:?}, expected a name like SIGSEGV or a number",
+            self.0
+        )
+    }
+}
+
+impl std::str::FromStr for Signal {
+    type Err = InvalidSignalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((signal, _)) = SIGNAL_NAMES.iter().find(|(_, name)| *name == s) {
+            return Ok(Signal(*signal));
+        }
+
+        s.parse()
+            .map(Signal)
+            .map_err(|_| InvalidSignalError(s.to_string()))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum SignalInput {
+    Number(i32),
+    Name(String),
+}
+
+impl std::convert::TryFrom<SignalInput> for Signal {
+    type Error = InvalidSignalError;
+
+    fn try_from(value: SignalInput) -> Result<Self, Self::Error> {
+        match value {
+            SignalInput::Number(signal) => Ok(Signal(signal)),
+            SignalInput::Name(name) => name.parse(),
+        }
+    }
+}
+
+impl From<Signal> for SignalInput {
+    fn from(signal: Signal) -> Self {
+        match signal.name() {
+            Some(name) => SignalInput::Name(name.to_string()),
+            None => SignalInput::Number(signal.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Signal;
+
+    #[test]
+    fn parse_signal() {
+        assert_eq!("SIGSEGV".parse(), Ok(Signal::SIGSEGV));
+        assert_eq!("11".parse(), Ok(Signal::SIGSEGV));
+        assert!("SEGV".parse::<Signal>().is_err());
+
+        assert_eq!(Signal(9).to_string(), "9 (SIGKILL)");
+        assert_eq!(Signal(64).to_string(), "64");
+    }
+
+    #[test]
+    fn serialize_signal() {
+        assert_eq!(
+            serde_json::to_string(&Signal::SIGSEGV).unwrap(),
+            "\"SIGSEGV\""
+        );
+        assert_eq!(
+            serde_json::from_str::<Signal>("\"SIGTERM\"").unwrap(),
+            Signal(15)
+        );
+        assert_eq!(serde_json::from_str::<Signal>("64").unwrap(), Signal(64));
+    }
+}
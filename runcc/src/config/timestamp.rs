@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampMode {
+    /// Local time of the wall clock
+    #[default]
+    Wall,
+    /// Time since runcc started
+    #[serde(alias = "since-start")]
+    SinceStart,
+}
+
+impl std::str::FromStr for TimestampMode {
+    type Err = serde_yaml::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s)
+    }
+}
@@ -0,0 +1,311 @@
+use std::{fmt::Display, fs, io, iter::Peekable, path::Path, str::Chars};
+
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq)]
+pub enum DotenvErrorKind {
+    InvalidKey(String),
+    MissingEqual,
+    UnterminatedQuote,
+    TrailingCharacters,
+}
+
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq)]
+pub struct DotenvError {
+    /// 1-based line number
+    pub line: usize,
+    pub kind: DotenvErrorKind,
+}
+
+impl std::error::Error for DotenvError {}
+
+impl Display for DotenvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            DotenvErrorKind::InvalidKey(key) => write!(f, "invalid env name \"{}\"", key),
+            DotenvErrorKind::MissingEqual => write!(f, "expected '='"),
+            DotenvErrorKind::UnterminatedQuote => write!(f, "unterminated quoted value"),
+            DotenvErrorKind::TrailingCharacters => {
+                write!(f, "unexpected characters after quoted value")
+            }
+        }
+    }
+}
+
+struct DotenvParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> DotenvParser<'a> {
+    fn error(&self, kind: DotenvErrorKind) -> DotenvError {
+        DotenvError {
+            line: self.line,
+            kind,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    /// skip whitespaces except line breaks
+    fn skip_spaces(&mut self) {
+        while let Some(c) = self.chars.peek() {
+            if *c != '\n' && c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.next() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn read_key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(c) = self.chars.peek() {
+            if *c == '=' || c.is_whitespace() {
+                break;
+            }
+            key.push(*c);
+            self.chars.next();
+        }
+        key
+    }
+
+    fn read_quoted(&mut self, quote: char) -> Result<String, DotenvError> {
+        let start_line = self.line;
+        let mut value = String::new();
+
+        loop {
+            match self.next() {
+                None => {
+                    return Err(DotenvError {
+                        line: start_line,
+                        kind: DotenvErrorKind::UnterminatedQuote,
+                    })
+                }
+                Some(c) if c == quote => return Ok(value),
+                // single quoted values are literal
+                Some('\\') if quote == '"' => match self.next() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('0') => value.push('\0'),
+                    Some(c @ ('\\' | '"' | '\'' | '$')) => value.push(c),
+                    // unknown escapes are kept, like in paths and regexes
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => {
+                        return Err(DotenvError {
+                            line: start_line,
+                            kind: DotenvErrorKind::UnterminatedQuote,
+                        })
+                    }
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn read_raw(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(c) = self.chars.peek() {
+            match *c {
+                '\n' => break,
+                // inline comment
+                '#' if value.is_empty() || value.ends_with(char::is_whitespace) => {
+                    self.skip_line();
+                    break;
+                }
+                c => {
+                    value.push(c);
+                    self.chars.next();
+                }
+            }
+        }
+
+        value.trim_end().to_string()
+    }
+
+    fn parse_one(&mut self) -> Result<Option<(String, String)>, DotenvError> {
+        loop {
+            match self.chars.peek() {
+                None => return Ok(None),
+                Some('#') => self.skip_line(),
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some(_) => break,
+            }
+        }
+
+        let mut key = self.read_key();
+        if key == "export" && self.chars.peek() != Some(&'=') {
+            self.skip_spaces();
+            key = self.read_key();
+        }
+
+        if !is_valid_key(&key) {
+            return Err(self.error(DotenvErrorKind::InvalidKey(key)));
+        }
+
+        self.skip_spaces();
+        if self.chars.next_if_eq(&'=').is_none() {
+            return Err(self.error(DotenvErrorKind::MissingEqual));
+        }
+        self.skip_spaces();
+
+        let value = match self.chars.peek() {
+            Some(&quote) if quote == '"' || quote == '\'' => {
+                self.chars.next();
+                let value = self.read_quoted(quote)?;
+
+                self.skip_spaces();
+                match self.chars.peek() {
+                    None | Some('\n') => {}
+                    Some('#') => self.skip_line(),
+                    Some(_) => return Err(self.error(DotenvErrorKind::TrailingCharacters)),
+                }
+
+                value
+            }
+            _ => self.read_raw(),
+        };
+
+        Ok(Some((key, value)))
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.chars().enumerate().all(|(i, c)| match c {
+            'a'..='z' | 'A'..='Z' | '_' => true,
+            '0'..='9' | '.' => i > 0,
+            _ => false,
+        })
+}
+
+/// Parse envs from the content of a dotenv file.
+///
+/// ```
+/// # use runcc::parse_dotenv;
+/// let content = r#"
+/// ## comment
+/// export PORT=8080 # inline comment
+/// GREETING="hello\nworld"
+/// RAW='$HOME\n'
+/// "#;
+///
+/// assert_eq!(parse_dotenv(content).unwrap(), [
+///     ("PORT".to_string(), "8080".to_string()),
+///     ("GREETING".to_string(), "hello\nworld".to_string()),
+///     ("RAW".to_string(), r"$HOME\n".to_string()),
+/// ]);
+/// ```
+///
+/// Values in double quotes support escapes `\n`, `\r`, `\t`, `\0`, `\\`, `\"`, `\'` and `\$`.
+/// Other backslashes are kept as is.
+/// Values in single quotes are literal. Quoted values can span multiple lines.
+pub fn parse_dotenv(content: &str) -> Result<Vec<(String, String)>, DotenvError> {
+    let mut parser = DotenvParser {
+        chars: content.chars().peekable(),
+        line: 1,
+    };
+
+    let mut envs = vec![];
+    while let Some(env) = parser.parse_one()? {
+        envs.push(env);
+    }
+
+    Ok(envs)
+}
+
+/// Read envs from a dotenv file.
+///
+/// Returns `Ok(None)` if the file doesn't exist.
+pub fn read_env_file(path: &Path) -> io::Result<Option<Vec<(String, String)>>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(io::Error::new(
+                err.kind(),
+                format!("failed to read env file {:?}: {}", path, err),
+            ))
+        }
+    };
+
+    parse_dotenv(&content).map(Some).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid env file {:?}: {}", path, err),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_dotenv, DotenvError, DotenvErrorKind};
+
+    #[test]
+    fn parse_dotenv_ok() {
+        for (content, envs) in [
+            ("", vec![]),
+            ("\n  # comment\n\n", vec![]),
+            ("A=1\nB = 2 \n", vec![("A", "1"), ("B", "2")]),
+            ("export A=1", vec![("A", "1")]),
+            ("export=1", vec![("export", "1")]),
+            ("A=", vec![("A", "")]),
+            ("A=a#b # comment", vec![("A", "a#b")]),
+            (
+                "URL=postgres://u@h/db?a=b",
+                vec![("URL", "postgres://u@h/db?a=b")],
+            ),
+            ("A=\"a # b\" # comment", vec![("A", "a # b")]),
+            (
+                "A=\"line1\nline2\"\nB=b",
+                vec![("A", "line1\nline2"), ("B", "b")],
+            ),
+            (r#"A="\"\t\\\$\q""#, vec![("A", "\"\t\\$\\q")]),
+            (r"A='\n'", vec![("A", r"\n")]),
+            (r"RE='\d+'", vec![("RE", r"\d+")]),
+            (r#"DIR="C:\Users\dev""#, vec![("DIR", r"C:\Users\dev")]),
+            ("A=1\r\nB=2\r\n", vec![("A", "1"), ("B", "2")]),
+        ] {
+            let envs: Vec<_> = envs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            assert_eq!(parse_dotenv(content).unwrap(), envs, "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn parse_dotenv_fail() {
+        for (content, line, kind) in [
+            ("A", 1, DotenvErrorKind::MissingEqual),
+            ("\nA B=1", 2, DotenvErrorKind::MissingEqual),
+            ("1A=1", 1, DotenvErrorKind::InvalidKey("1A".to_string())),
+            ("A-B=1", 1, DotenvErrorKind::InvalidKey("A-B".to_string())),
+            ("A=\"1\n\n", 1, DotenvErrorKind::UnterminatedQuote),
+            ("A='1'2", 1, DotenvErrorKind::TrailingCharacters),
+        ] {
+            assert_eq!(parse_dotenv(content), Err(DotenvError { line, kind }));
+        }
+    }
+}
@@ -76,7 +76,7 @@ fn try_hex_to_u8(hex: char) -> Option<u8> {
     })
 }
 
-pub(crate) fn match_one_env(program: &str) -> (Option<(&str, Cow<str>)>, &str) {
+pub(crate) fn match_one_env(program: &str) -> (Option<(&str, Cow<'_, str>)>, &str) {
     let mut state_data = State::None(0);
     let state = &mut state_data;
 
@@ -303,7 +303,7 @@ pub(crate) fn match_one_env(program: &str) -> (Option<(&str, Cow<str>)>, &str) {
             key,
             value,
             program_start,
-        } => (Some((key, value)), &program[program_start..].trim()),
+        } => (Some((key, value)), program[program_start..].trim()),
         State::KeyAndValue {
             key,
             value_state: ValueState::QuoteEnd(value),
@@ -336,7 +336,8 @@ pub(crate) fn match_one_env(program: &str) -> (Option<(&str, Cow<str>)>, &str) {
 /// # use runcc::match_program_with_envs;
 /// assert_eq!(match_program_with_envs(r#"MY_KEY="\n \x25 \u{26}" cargo run"#), ("cargo run", Some(vec![("MY_KEY", "\n % &".into())])));
 /// ```
-pub fn match_program_with_envs(mut program: &str) -> (&str, Option<Vec<(&str, Cow<str>)>>) {
+#[allow(clippy::type_complexity)]
+pub fn match_program_with_envs(mut program: &str) -> (&str, Option<Vec<(&str, Cow<'_, str>)>>) {
     let mut envs = vec![];
 
     loop {
@@ -346,7 +347,7 @@ pub fn match_program_with_envs(mut program: &str) -> (&str, Option<Vec<(&str, Co
             program = new_program;
             envs.push(env);
         } else {
-            return (new_program, if envs.len() > 0 { Some(envs) } else { None });
+            return (new_program, if envs.is_empty() { None } else { Some(envs) });
         }
     }
 }
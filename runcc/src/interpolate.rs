@@ -0,0 +1,326 @@
+use std::{borrow::Cow, fmt::Display};
+
+#[non_exhaustive]
+#[derive(Debug, PartialEq, Eq)]
+pub enum InterpolateError {
+    /// `${VAR:?message}` while `VAR` is not set or empty
+    MissingVariable { name: String, message: String },
+    /// `${` without matching `}`
+    UnclosedBrace(String),
+    /// `${...}` with invalid syntax
+    InvalidExpression(String),
+}
+
+impl std::error::Error for InterpolateError {}
+
+impl Display for InterpolateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpolateError::MissingVariable { name, message } => {
+                write!(f, "required variable {} is not set", name)?;
+                if !message.is_empty() {
+                    write!(f, ": {}", message)?;
+                }
+                Ok(())
+            }
+            InterpolateError::UnclosedBrace(s) => write!(f, "unclosed \"${{\" in \"{}\"", s),
+            InterpolateError::InvalidExpression(s) => {
+                write!(f, "invalid variable expression \"${{{}}}\"", s)
+            }
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().enumerate().all(|(i, c)| match c {
+            'a'..='z' | 'A'..='Z' | '_' => true,
+            '0'..='9' => i > 0,
+            _ => false,
+        })
+}
+
+/// Find the index of `}` closing `${` in `s`, where `s` starts after `${`
+fn find_closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut chars = s.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '$' => {
+                // `$${` is escaped `${`, but it also needs a `}`
+                while chars.next_if(|(_, c)| *c == '$').is_some() {}
+                if chars.next_if(|(_, c)| *c == '{').is_some() {
+                    depth += 1;
+                }
+            }
+            '}' => {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Interpolate variables in `s`.
+///
+/// - `${VAR}` is replaced with the value of `VAR`, or empty string if `VAR` is not set
+/// - `${VAR:-default}` is replaced with `default` if `VAR` is not set or empty.
+///   `default` is also interpolated.
+/// - `${VAR:?message}` fails with `message` if `VAR` is not set or empty
+/// - `$${` is replaced with literal `${`
+///
+/// Other `$` are kept as is, so `$VAR` and `$$` are left for the shell.
+///
+/// ```
+/// # use runcc::interpolate;
+/// let lookup = |name: &str| (name == "PORT").then(|| "3000".to_string());
+///
+/// assert_eq!(interpolate("--port=${PORT}", &lookup).unwrap(), "--port=3000");
+/// assert_eq!(interpolate("${HOST:-localhost}:${PORT:-8080}", &lookup).unwrap(), "localhost:3000");
+/// assert_eq!(interpolate("echo $${PORT} $$", &lookup).unwrap(), "echo ${PORT} $$");
+/// assert!(interpolate("${HOST:?host is required}", &lookup).is_err());
+/// ```
+pub fn interpolate<'a, F>(s: &'a str, lookup: &F) -> Result<Cow<'a, str>, InterpolateError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    interpolate_with(s, lookup, false)
+}
+
+/// Interpolate variables in a script run by a shell, like `sh -c <script>`.
+///
+/// It's like [`interpolate`], but `${...}` is kept as is for the shell
+/// if its variable is not set or it is not valid interpolation syntax,
+/// so shell variables and expansions like `${f%.txt}` still work.
+///
+/// ```
+/// # use runcc::interpolate_script;
+/// let lookup = |name: &str| (name == "PORT").then(|| "3000".to_string());
+///
+/// assert_eq!(
+///     interpolate_script("for f in *.txt; do mv $f ${f%.txt}.md; done", &lookup).unwrap(),
+///     "for f in *.txt; do mv $f ${f%.txt}.md; done",
+/// );
+/// assert_eq!(
+///     interpolate_script("serve --port=${PORT} --host=${HOST:-localhost}", &lookup).unwrap(),
+///     "serve --port=3000 --host=${HOST:-localhost}",
+/// );
+/// ```
+pub fn interpolate_script<'a, F>(s: &'a str, lookup: &F) -> Result<Cow<'a, str>, InterpolateError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    interpolate_with(s, lookup, true)
+}
+
+/// Interpolate `s`, keeping unknown `${...}` as is if `keep_unknown`
+fn interpolate_with<'a, F>(
+    s: &'a str,
+    lookup: &F,
+    keep_unknown: bool,
+) -> Result<Cow<'a, str>, InterpolateError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if !s.contains("${") {
+        return Ok(s.into());
+    }
+
+    let mut res = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(i) = rest.find('$') {
+        res.push_str(&rest[..i]);
+        let after = &rest[(i + 1)..];
+
+        if let Some(after) = after.strip_prefix("${") {
+            res.push_str("${");
+            rest = after;
+            continue;
+        }
+
+        let after = match after.strip_prefix('{') {
+            Some(after) => after,
+            None => {
+                res.push('$');
+                rest = after;
+                continue;
+            }
+        };
+
+        let end = match find_closing_brace(after) {
+            Some(end) => end,
+            None if keep_unknown => {
+                res.push_str(&rest[i..]);
+                rest = "";
+                break;
+            }
+            None => return Err(InterpolateError::UnclosedBrace(s.to_string())),
+        };
+        let expr = &after[..end];
+        rest = &after[(end + 1)..];
+
+        let (name, op) = match expr.find(':') {
+            Some(i) => (&expr[..i], Some(&expr[(i + 1)..])),
+            None => (expr, None),
+        };
+
+        let is_valid =
+            is_valid_name(name) && op.is_none_or(|op| op.starts_with('-') || op.starts_with('?'));
+        let value = if is_valid { lookup(name) } else { None };
+
+        if keep_unknown && value.is_none() {
+            res.push_str("${");
+            res.push_str(expr);
+            res.push('}');
+            continue;
+        }
+
+        if !is_valid {
+            return Err(InterpolateError::InvalidExpression(expr.to_string()));
+        }
+
+        let value = value.filter(|v| !v.is_empty());
+
+        match op {
+            None => res.push_str(value.as_deref().unwrap_or("")),
+            Some(op) => {
+                if let Some(default) = op.strip_prefix('-') {
+                    match value {
+                        Some(value) => res.push_str(&value),
+                        None => res.push_str(&interpolate_with(default, lookup, keep_unknown)?),
+                    }
+                } else if let Some(message) = op.strip_prefix('?') {
+                    match value {
+                        Some(value) => res.push_str(&value),
+                        None => {
+                            return Err(InterpolateError::MissingVariable {
+                                name: name.to_string(),
+                                message: message.to_string(),
+                            })
+                        }
+                    }
+                } else {
+                    return Err(InterpolateError::InvalidExpression(expr.to_string()));
+                }
+            }
+        }
+    }
+
+    res.push_str(rest);
+
+    Ok(res.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interpolate, interpolate_script, InterpolateError};
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "A" => Some("a".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interpolate_ok() {
+        for (s, res) in [
+            ("", ""),
+            ("no vars", "no vars"),
+            ("$A $$ $", "$A $$ $"),
+            ("${A}", "a"),
+            ("x${A}y${A}z", "xayaz"),
+            ("${UNSET}", ""),
+            ("${UNSET:-default}", "default"),
+            ("${EMPTY:-default}", "default"),
+            ("${A:-default}", "a"),
+            ("${UNSET:-}", ""),
+            ("${UNSET:-${A}/b}", "a/b"),
+            ("${UNSET:-${UNSET:-c}}", "c"),
+            ("${A:?required}", "a"),
+            ("$${A}", "${A}"),
+            ("$$${A}", "$${A}"),
+            ("${UNSET:-$${A}}", "${A}"),
+            ("你好${A}", "你好a"),
+        ] {
+            assert_eq!(interpolate(s, &lookup).unwrap(), res, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn interpolate_fail() {
+        for (s, err) in [
+            (
+                "${UNSET:?please set UNSET}",
+                InterpolateError::MissingVariable {
+                    name: "UNSET".to_string(),
+                    message: "please set UNSET".to_string(),
+                },
+            ),
+            (
+                "${EMPTY:?}",
+                InterpolateError::MissingVariable {
+                    name: "EMPTY".to_string(),
+                    message: String::new(),
+                },
+            ),
+            (
+                "${UNSET:-${UNSET:?}}",
+                InterpolateError::MissingVariable {
+                    name: "UNSET".to_string(),
+                    message: String::new(),
+                },
+            ),
+            ("${A", InterpolateError::UnclosedBrace("${A".to_string())),
+            ("${}", InterpolateError::InvalidExpression("".to_string())),
+            (
+                "${1A}",
+                InterpolateError::InvalidExpression("1A".to_string()),
+            ),
+            (
+                "${A:+b}",
+                InterpolateError::InvalidExpression("A:+b".to_string()),
+            ),
+        ] {
+            assert_eq!(interpolate(s, &lookup), Err(err), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn interpolate_script_keeps_shell_syntax() {
+        for (s, res) in [
+            ("${A} ${EMPTY}", "a "),
+            ("${UNSET:-${A}}", "${UNSET:-${A}}"),
+            ("${A:-default}", "a"),
+            ("$${A}", "${A}"),
+            // shell variables which are not set in runcc
+            (
+                "for x in 1 2; do echo ${x}; done",
+                "for x in 1 2; do echo ${x}; done",
+            ),
+            ("${UNSET:?required}", "${UNSET:?required}"),
+            // shell expansions
+            ("mv $f ${f%.txt}.md", "mv $f ${f%.txt}.md"),
+            ("echo ${#arr[@]} ${A/a/b}", "echo ${#arr[@]} ${A/a/b}"),
+            ("${A:+b} ${A", "${A:+b} ${A"),
+        ] {
+            assert_eq!(interpolate_script(s, &lookup).unwrap(), res, "{:?}", s);
+        }
+
+        assert_eq!(
+            interpolate_script("${EMPTY:?required}", &lookup),
+            Err(InterpolateError::MissingVariable {
+                name: "EMPTY".to_string(),
+                message: "required".to_string(),
+            })
+        );
+    }
+}
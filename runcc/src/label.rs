@@ -1,3 +1,6 @@
+use crate::unicode::{str_width, take_width, take_width_end};
+use crate::{LabelAlign, LabelTruncate};
+
 #[derive(Debug, Clone)]
 pub struct Label {
     label: String,
@@ -18,20 +21,161 @@ impl Label {
     }
 
     pub fn from_label(label: String, max_label_length: usize) -> Self {
-        let len = label.len();
-        let display = if len > max_label_length {
-            let len_trim = std::cmp::min(len - max_label_length, 3);
-
-            let label = &label[0..(max_label_length - len_trim)];
-            let padding = ".".repeat(len_trim);
-            Some(format!("{}{}", label, padding))
-        } else if len < max_label_length {
-            let padding = " ".repeat(max_label_length - len);
-            Some(format!("{}{}", label, padding))
+        Self::format(
+            label,
+            max_label_length,
+            LabelTruncate::default(),
+            LabelAlign::default(),
+        )
+    }
+
+    /// Truncate or pad `label` to `max_label_length` columns in terminals for display.
+    ///
+    /// Labels are measured by display width and truncated on grapheme boundaries,
+    /// so that CJK characters and emoji are aligned.
+    pub fn format(
+        label: String,
+        max_label_length: usize,
+        truncate: LabelTruncate,
+        align: LabelAlign,
+    ) -> Self {
+        let width = str_width(&label);
+        let display = if width > max_label_length {
+            Some(truncate_label(&label, width, max_label_length, truncate))
+        } else if width < max_label_length {
+            let padding = max_label_length - width;
+            let (left, right) = match align {
+                LabelAlign::Left => (0, padding),
+                LabelAlign::Right => (padding, 0),
+                LabelAlign::Center => (padding / 2, padding - padding / 2),
+            };
+            Some(format!(
+                "{}{}{}",
+                " ".repeat(left),
+                label,
+                " ".repeat(right)
+            ))
         } else {
             None
         };
 
         Self::new(label, display)
     }
+
+    /// Prepend the index of the command to the display, like `2:api`.
+    ///
+    /// The index is right aligned to `width` columns.
+    pub fn with_index(self, index: usize, width: usize) -> Self {
+        let display = format!("{:>width$}:{}", index, self.display(), width = width);
+        Self::new(self.label, Some(display))
+    }
+}
+
+/// Truncate `label` of `width` columns to exactly `max` columns
+fn truncate_label(label: &str, width: usize, max: usize, truncate: LabelTruncate) -> String {
+    let truncated = match truncate {
+        LabelTruncate::End => truncate_end(label, width, max),
+        LabelTruncate::Middle => {
+            let dots = 3;
+            if max <= dots {
+                truncate_end(label, width, max)
+            } else {
+                let tail = (max - dots) / 2;
+                let head = max - dots - tail;
+                format!(
+                    "{}{}{}",
+                    take_width(label, head),
+                    ".".repeat(dots),
+                    take_width_end(label, tail)
+                )
+            }
+        }
+        LabelTruncate::LastSegment => {
+            let segment = label
+                .trim_end()
+                .rsplit(|c: char| c.is_whitespace() || c == '/' || c == '\\')
+                .next()
+                .unwrap_or_default();
+            let segment_width = str_width(segment);
+
+            if segment.is_empty() {
+                truncate_end(label, width, max)
+            } else if segment_width > max {
+                truncate_end(segment, segment_width, max)
+            } else {
+                segment.to_string()
+            }
+        }
+    };
+
+    // a wide char may not fit into the last column
+    let padding = max.saturating_sub(str_width(&truncated));
+    format!("{}{}", truncated, " ".repeat(padding))
+}
+
+/// Keep the start and replace up to 3 columns at the end with `.`
+fn truncate_end(label: &str, width: usize, max: usize) -> String {
+    let dots = (width - max).min(3).min(max);
+    format!("{}{}", take_width(label, max - dots), ".".repeat(dots))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Label;
+    use crate::{LabelAlign, LabelTruncate};
+
+    fn display(label: &str, max: usize, truncate: LabelTruncate) -> String {
+        Label::format(label.to_string(), max, truncate, LabelAlign::Left)
+            .display()
+            .to_string()
+    }
+
+    #[test]
+    fn align_and_index() {
+        let format = |align| Label::format("api".to_string(), 8, LabelTruncate::End, align);
+
+        assert_eq!(format(LabelAlign::Right).display(), "     api");
+        assert_eq!(format(LabelAlign::Center).display(), "  api   ");
+        assert_eq!(
+            format(LabelAlign::Left).with_index(2, 2).display(),
+            " 2:api     "
+        );
+    }
+
+    #[test]
+    fn pad_by_width() {
+        assert_eq!(display("api", 6, LabelTruncate::End), "api   ");
+        assert_eq!(display("服务", 6, LabelTruncate::End), "服务  ");
+        assert_eq!(display("👍 web", 6, LabelTruncate::End), "👍 web");
+    }
+
+    #[test]
+    fn truncate_labels() {
+        let label = "cargo run -p very-long-crate-name";
+        assert_eq!(display(label, 16, LabelTruncate::End), "cargo run -p ...");
+        assert_eq!(
+            display(label, 16, LabelTruncate::Middle),
+            "cargo r...e-name"
+        );
+        assert_eq!(
+            display(label, 20, LabelTruncate::LastSegment),
+            "very-long-crate-name"
+        );
+        assert_eq!(
+            display(label, 24, LabelTruncate::LastSegment),
+            "very-long-crate-name    "
+        );
+        assert_eq!(display(label, 8, LabelTruncate::LastSegment), "very-...");
+        assert_eq!(display("api", 2, LabelTruncate::End), "a.");
+    }
+
+    #[test]
+    fn truncate_on_grapheme_boundaries() {
+        assert_eq!(display("服务服务", 7, LabelTruncate::End), "服务服.");
+        assert_eq!(display("服务服务x", 6, LabelTruncate::End), "服... ");
+        assert_eq!(
+            display("e\u{301}e\u{301}e\u{301}e\u{301}x", 4, LabelTruncate::End),
+            "e\u{301}e\u{301}e\u{301}."
+        );
+    }
 }
@@ -1,8 +1,12 @@
 pub mod config;
+mod dotenv;
 mod env;
+mod interpolate;
 
 pub use config::*;
+pub use dotenv::*;
 pub use env::*;
+pub use interpolate::*;
 
 pub mod run;
 
@@ -11,5 +15,6 @@ pub mod label;
 #[cfg(feature = "cli")]
 pub mod cli;
 
-#[cfg(feature = "auto_ansi_escape")]
-mod ansi_escape;
+#[cfg(feature = "cli")]
+mod terminal;
+mod unicode;
@@ -1,9 +1,16 @@
-use std::{io, process::Stdio};
+use std::{
+    io,
+    process::Stdio,
+    time::{Duration, Instant, SystemTime},
+};
 
 use tokio::process::{Child, ChildStderr, ChildStdout, Command};
 use tokio::sync::oneshot;
 
-use super::super::kill;
+use super::super::{kill, memory};
+
+/// Interval to sample peak memory of a running command
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
 
 pub struct CommandInitialized<T> {
     command: Command,
@@ -32,6 +39,14 @@ fn start_kill_child_process<T>(
     }
 }
 
+/// Program and arguments of `command`, lossily converted to strings
+pub(crate) fn command_argv(command: &std::process::Command) -> Vec<String> {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
 impl<T> CommandInitialized<T> {
     pub fn new(command: Command, data: T) -> Self {
         Self { command, data }
@@ -43,38 +58,70 @@ impl<T> CommandInitialized<T> {
         let Self { mut command, data } = self;
         let (kill_sender, kill_receiver) = oneshot::channel::<kill::KillCommandReason<R>>();
 
+        let argv = command_argv(command.as_std());
+        let spawned_at = SystemTime::now();
+        let started = Instant::now();
+
         let mut child = command
-            .env("CARGO_TERM_COLOR", "always")
-            // yarn force color https://classic.yarnpkg.com/en/docs/cli/#toc-verbose
-            .env("FORCE_COLOR", "true")
             .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()?;
-        let stdout = child.stdout.take().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "Could not capture standard output.")
-        })?;
-        let stderr = child.stderr.take().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "Could not capture standard error.")
-        })?;
+        let pid = child.id();
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("Could not capture standard output."))?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| io::Error::other("Could not capture standard error."))?;
 
         let join_handle = tokio::spawn(async move {
-            tokio::select! {
-                status = child.wait() => (status, None),
-                kill_reason = kill_receiver => {
-                    let kill_status = if let Ok(kill_reason) = kill_reason {
-                        start_kill_child_process(&mut child, kill_reason)
-                    } else {
-                        kill::KillJoinHandleFinalStatus::SenderDisconnected
-                    };
-                    let status = child.wait().await;
-                    (status, Some(kill_status))
+            let wait = async {
+                tokio::select! {
+                    status = child.wait() => (status, None),
+                    kill_reason = kill_receiver => {
+                        let kill_status = if let Ok(kill_reason) = kill_reason {
+                            start_kill_child_process(&mut child, kill_reason)
+                        } else {
+                            kill::KillJoinHandleFinalStatus::SenderDisconnected
+                        };
+                        let status = child.wait().await;
+                        (status, Some(kill_status))
+                    }
                 }
+            };
+            tokio::pin!(wait);
+
+            // peak memory can't be read after the process is reaped, so sample it while waiting
+            let mut peak_memory = None;
+            let mut interval = tokio::time::interval(MEMORY_SAMPLE_INTERVAL);
+            let (exit_status, killed) = loop {
+                tokio::select! {
+                    res = &mut wait => break res,
+                    _ = interval.tick() => {
+                        if let Some(pid) = pid {
+                            peak_memory = memory::peak_memory(pid).or(peak_memory);
+                        }
+                    }
+                }
+            };
+
+            super::CommandExit {
+                exit_status,
+                killed,
+                pid,
+                argv,
+                spawned_at,
+                exited_at: SystemTime::now(),
+                duration: started.elapsed(),
+                peak_memory,
             }
         });
 
         Ok((
-            super::CommandSpawned::new(data, kill_sender, join_handle),
+            super::CommandSpawned::new(data, pid, kill_sender, join_handle),
             stdout,
             stderr,
         ))
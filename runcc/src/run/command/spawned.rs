@@ -1,36 +1,62 @@
 use std::io;
 use std::process::ExitStatus;
+use std::time::{Duration, SystemTime};
 use tokio::task::JoinHandle;
 
 use super::super::kill;
 
 #[derive(Clone)]
 pub struct SharedCommandSpawned<T, R> {
-    data: T,
-    killer: kill::CommandKiller<R>,
+    pub data: T,
+    pub killer: kill::CommandKiller<R>,
+}
+
+/// What the task waiting for a command returns
+pub(super) struct CommandExit<T> {
+    pub(super) exit_status: io::Result<ExitStatus>,
+    pub(super) killed: Option<kill::KillJoinHandleFinalStatus<T>>,
+    pub(super) pid: Option<u32>,
+    pub(super) argv: Vec<String>,
+    pub(super) spawned_at: SystemTime,
+    pub(super) exited_at: SystemTime,
+    pub(super) duration: Duration,
+    pub(super) peak_memory: Option<u64>,
 }
 
-pub(super) type CommandTokioJoinHandle<T> = JoinHandle<(
-    io::Result<ExitStatus>,
-    Option<kill::KillJoinHandleFinalStatus<T>>,
-)>;
+pub(super) type CommandTokioJoinHandle<T> = JoinHandle<CommandExit<T>>;
 
 pub struct CommandJoinHandle<R>(CommandTokioJoinHandle<R>);
 
 impl<R> CommandJoinHandle<R> {
     pub async fn join(self) -> super::CommandStopped<(), R> {
-        let (exit_status, killed) = self.0.await.expect("command task handle should not panic");
+        let CommandExit {
+            exit_status,
+            killed,
+            pid,
+            argv,
+            spawned_at,
+            exited_at,
+            duration,
+            peak_memory,
+        } = self.0.await.expect("command task handle should not panic");
 
         super::CommandStopped {
             data: (),
             exit_status,
             killed,
+            pid,
+            argv,
+            spawned_at: Some(spawned_at),
+            exited_at,
+            duration,
+            peak_memory,
         }
     }
 }
 
 pub struct CommandSpawned<T, R> {
     pub data: T,
+    pub pid: Option<u32>,
     pub killer: kill::CommandKiller<R>,
     pub join_handle: CommandJoinHandle<R>,
 }
@@ -38,11 +64,13 @@ pub struct CommandSpawned<T, R> {
 impl<T, R> CommandSpawned<T, R> {
     pub(super) fn new(
         data: T,
+        pid: Option<u32>,
         kill_sender: kill::KillSender<R>,
         join_handle: CommandTokioJoinHandle<R>,
     ) -> Self {
         Self {
             data,
+            pid,
             killer: kill::CommandKiller::new(kill_sender),
             join_handle: CommandJoinHandle(join_handle),
         }
@@ -63,6 +91,7 @@ impl<T, R> CommandSpawned<T, R> {
     pub fn with_data<S>(self, new_data: S) -> (T, CommandSpawned<S, R>) {
         let Self {
             data,
+            pid,
             join_handle,
             killer,
         } = self;
@@ -71,6 +100,7 @@ impl<T, R> CommandSpawned<T, R> {
             data,
             CommandSpawned {
                 data: new_data,
+                pid,
                 join_handle,
                 killer,
             },
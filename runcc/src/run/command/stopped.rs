@@ -1,4 +1,8 @@
-use std::{io, process::ExitStatus};
+use std::{
+    io,
+    process::ExitStatus,
+    time::{Duration, SystemTime},
+};
 
 use super::super::kill;
 
@@ -7,14 +11,51 @@ pub struct CommandStopped<T, R> {
     pub data: T,
     pub exit_status: io::Result<ExitStatus>,
     pub killed: Option<kill::KillJoinHandleFinalStatus<R>>,
+    /// `None` if the command failed to spawn
+    pub pid: Option<u32>,
+    /// Program and arguments, lossily converted to strings
+    pub argv: Vec<String>,
+    /// When the command was spawned, `None` if it failed to spawn
+    pub spawned_at: Option<SystemTime>,
+    /// When the command exited or failed to spawn
+    pub exited_at: SystemTime,
+    /// Time from spawn to exit, measured with a monotonic clock
+    pub duration: Duration,
+    /// Peak resident memory in bytes, sampled while the command is running.
+    ///
+    /// It's of the spawned process only, not including its children,
+    /// so it's the memory of the shell for commands run as a script.
+    /// Only available on Linux.
+    pub peak_memory: Option<u64>,
 }
 
 impl<T, R> CommandStopped<T, R> {
+    /// A command which failed to spawn with `err`
+    pub(crate) fn spawn_failed(data: T, argv: Vec<String>, err: io::Error) -> Self {
+        Self {
+            data,
+            exit_status: Err(err),
+            killed: None,
+            pid: None,
+            argv,
+            spawned_at: None,
+            exited_at: SystemTime::now(),
+            duration: Duration::ZERO,
+            peak_memory: None,
+        }
+    }
+
     pub fn with_data<S>(self, new_data: S) -> (T, CommandStopped<S, R>) {
         let Self {
             data,
             exit_status,
             killed,
+            pid,
+            argv,
+            spawned_at,
+            exited_at,
+            duration,
+            peak_memory,
         } = self;
         (
             data,
@@ -22,6 +63,12 @@ impl<T, R> CommandStopped<T, R> {
                 data: new_data,
                 exit_status,
                 killed,
+                pid,
+                argv,
+                spawned_at,
+                exited_at,
+                duration,
+                peak_memory,
             },
         )
     }
@@ -0,0 +1,22 @@
+/// Peak resident memory of a running process in bytes,
+/// not including its children.
+///
+/// Read from `VmHWM` in `/proc/{pid}/status`, so it's only available on Linux.
+#[cfg(target_os = "linux")]
+pub(super) fn peak_memory(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) fn peak_memory(_pid: u32) -> Option<u64> {
+    None
+}
@@ -1,4 +1,5 @@
 mod command;
+mod memory;
 mod report;
 mod system;
 
@@ -1,3 +1,11 @@
+use std::process::ExitStatus;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Serialize, Serializer};
+
+use super::{kill, CommandStopped, LabeledCommandData};
+use crate::Signal;
+
 #[derive(Debug, Clone, Copy)]
 #[non_exhaustive]
 pub struct CommandSystemSimpleReport {
@@ -10,3 +18,239 @@ impl CommandSystemSimpleReport {
         self.command_count_total - self.command_count_success
     }
 }
+
+/// Report of all commands after they exit, in the order of [`RunConfig::commands`](crate::RunConfig::commands).
+///
+/// Times are serialized as seconds since unix epoch, and durations as seconds.
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct CommandSystemReport {
+    pub commands: Vec<CommandReport>,
+}
+
+impl CommandSystemReport {
+    pub fn command_count_total(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn command_count_success(&self) -> usize {
+        self.commands.iter().filter(|cmd| cmd.success()).count()
+    }
+
+    pub fn command_count_failed(&self) -> usize {
+        self.command_count_total() - self.command_count_success()
+    }
+
+    pub fn to_simple(&self) -> CommandSystemSimpleReport {
+        CommandSystemSimpleReport {
+            command_count_total: self.command_count_total(),
+            command_count_success: self.command_count_success(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct CommandReport {
+    /// Index of the command in [`RunConfig::commands`](crate::RunConfig::commands)
+    pub index: usize,
+    pub label: String,
+    pub pid: Option<u32>,
+    /// Program and arguments
+    pub argv: Vec<String>,
+    /// Error when spawning the command
+    pub spawn_error: Option<String>,
+    /// Error when waiting for the command to exit
+    pub wait_error: Option<String>,
+    /// `None` if the command failed to spawn or to be waited
+    pub exit_status: Option<ExitStatusReport>,
+    /// Whether runcc killed or tried to kill the command
+    pub kill: Option<KillReport>,
+    #[serde(serialize_with = "serialize_optional_time")]
+    pub spawned_at: Option<SystemTime>,
+    #[serde(serialize_with = "serialize_time")]
+    pub exited_at: SystemTime,
+    #[serde(serialize_with = "serialize_duration")]
+    pub duration: Duration,
+    /// Peak resident memory of the spawned process in bytes,
+    /// not including its children. Only available on Linux.
+    pub peak_memory: Option<u64>,
+}
+
+impl CommandReport {
+    pub fn success(&self) -> bool {
+        self.exit_status.is_some_and(|status| status.success)
+    }
+
+    pub fn from_stopped(cmd: &CommandStopped<LabeledCommandData, LabeledCommandData>) -> Self {
+        let (exit_status, error) = match &cmd.exit_status {
+            Ok(status) => (Some(ExitStatusReport::from(*status)), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let (spawn_error, wait_error) = if cmd.spawned_at.is_some() {
+            (None, error)
+        } else {
+            (error, None)
+        };
+
+        Self {
+            index: cmd.data.index,
+            label: cmd.data.label.label().to_string(),
+            pid: cmd.pid,
+            argv: cmd.argv.clone(),
+            spawn_error,
+            wait_error,
+            exit_status,
+            kill: cmd.killed.as_ref().and_then(KillReport::new),
+            spawned_at: cmd.spawned_at,
+            exited_at: cmd.exited_at,
+            duration: cmd.duration,
+            peak_memory: cmd.peak_memory,
+        }
+    }
+}
+
+/// [`ExitStatus`] decoded in a portable way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct ExitStatusReport {
+    pub success: bool,
+    /// Exit code, which is `None` if the command is terminated by a signal
+    pub code: Option<i32>,
+    /// Signal which terminated the command, only on Unix
+    pub signal: Option<i32>,
+    /// Name of `signal` like `SIGSEGV`, only for signals with portable numbers
+    pub signal_name: Option<&'static str>,
+    /// Whether the command dumped core when terminated by `signal`, only on Unix
+    pub core_dumped: bool,
+}
+
+impl From<ExitStatus> for ExitStatusReport {
+    fn from(status: ExitStatus) -> Self {
+        #[cfg(unix)]
+        let (signal, core_dumped) = {
+            use std::os::unix::process::ExitStatusExt;
+            (status.signal(), status.core_dumped())
+        };
+        #[cfg(not(unix))]
+        let (signal, core_dumped) = (None, false);
+
+        Self {
+            success: status.success(),
+            code: status.code(),
+            signal,
+            signal_name: signal.and_then(|signal| Signal(signal).name()),
+            core_dumped,
+        }
+    }
+}
+
+/// Like `code 1`, `signal 9 (SIGKILL)` or `signal 11 (SIGSEGV), core dumped`
+impl std::fmt::Display for ExitStatusReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "code {}", code)?,
+            (None, Some(signal)) => write!(f, "signal {}", Signal(signal))?,
+            (None, None) => f.write_str("unknown")?,
+        }
+
+        if self.core_dumped {
+            f.write_str(", core dumped")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Why runcc killed a command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum KillReport {
+    Killed {
+        reason: String,
+    },
+    FailedToKill {
+        reason: String,
+        error: String,
+    },
+    /// The command exited before it was killed
+    AlreadyExited {
+        reason: String,
+    },
+}
+
+impl KillReport {
+    /// `None` if runcc didn't try to kill the command
+    pub fn new(status: &kill::KillJoinHandleFinalStatus<LabeledCommandData>) -> Option<Self> {
+        use kill::{CommandAlreadyExitedKind, KillJoinHandleFinalStatus as KS};
+
+        match status {
+            KS::Killed(reason) => Some(KillReport::Killed {
+                reason: reason.to_string(),
+            }),
+            KS::FailedToKill { reason, error } => Some(KillReport::FailedToKill {
+                reason: reason.to_string(),
+                error: error.to_string(),
+            }),
+            KS::AlreadyExited(CommandAlreadyExitedKind::ProcessExited(reason)) => {
+                Some(KillReport::AlreadyExited {
+                    reason: reason.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn unix_secs(time: &SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0.0, |duration| duration.as_secs_f64())
+}
+
+fn serialize_time<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(unix_secs(time))
+}
+
+fn serialize_optional_time<S: Serializer>(
+    time: &Option<SystemTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    time.as_ref().map(unix_secs).serialize(serializer)
+}
+
+fn serialize_duration<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{serialize_optional_time, KillReport};
+
+    #[test]
+    fn serialize_kill_report() {
+        let report = KillReport::FailedToKill {
+            reason: "Ctrl-C signal".to_string(),
+            error: "denied".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"result":"failed_to_kill","reason":"Ctrl-C signal","error":"denied"}"#
+        );
+    }
+
+    #[test]
+    fn serialize_time_as_secs() {
+        let time = UNIX_EPOCH + Duration::from_millis(1500);
+        let json = serialize_optional_time(&Some(time), serde_json::value::Serializer).unwrap();
+        assert_eq!(json, serde_json::json!(1.5));
+
+        let json = serialize_optional_time(&None, serde_json::value::Serializer).unwrap();
+        assert_eq!(json, serde_json::Value::Null);
+    }
+}
@@ -1,5 +1,6 @@
 use std::{
-    cmp, mem,
+    cmp, io, mem,
+    path::PathBuf,
     sync::{Arc, Mutex},
 };
 
@@ -9,12 +10,12 @@ use tokio::{
     task::JoinHandle,
 };
 
-use crate::{label::Label, KillBehavior, RunConfig};
+use crate::{label::Label, Color, Encoding, KillBehavior, OutputVisibility, RunConfig};
 
 use super::kill;
 use super::{
-    command::{CommandInitialized, CommandSpawned, CommandStopped},
-    CommandSystemSimpleReport,
+    command::{command_argv, CommandInitialized, CommandSpawned, CommandStopped},
+    CommandReport, CommandSystemReport, CommandSystemSimpleReport,
 };
 
 enum CommandState<T> {
@@ -57,8 +58,8 @@ where
 {
     fn spawn_with_plugin<I>(commands: I, kill_behavior: KillBehavior, plugin: P) -> Self
     where
-        I: IntoIterator<Item = (Command, P::CommandInitialData)>,
-        P: ,
+        I: IntoIterator<Item = (io::Result<Command>, P::CommandInitialData)>,
+        P:,
     {
         let commands: Vec<_> = commands.into_iter().collect();
         let (tx, mut rx) = mpsc::channel(cmp::min(commands.len(), 1));
@@ -71,21 +72,21 @@ where
                 let tx = tx.clone();
                 let plugin = plugin.clone();
 
-                let spawned = CommandInitialized::new(command, ()).spawn::<T>();
+                let argv = command
+                    .as_ref()
+                    .map_or_else(|_| vec![], |command| command_argv(command.as_std()));
+                let spawned =
+                    command.and_then(|command| CommandInitialized::new(command, ()).spawn::<T>());
 
                 let (cmd, stdout, stderr) = match spawned {
                     Ok((cmd, stdout, stderr)) => (cmd.with_data(data).1, stdout, stderr),
                     Err(err) => {
                         let data = plugin.initialize_spawn_failed_command_data(data);
-                        let cmd = Arc::new(CommandStopped {
-                            data,
-                            exit_status: Err(err),
-                            killed: None,
-                        });
+                        let cmd = Arc::new(CommandStopped::spawn_failed(data, argv, err));
                         let mutex = Arc::new(Mutex::new(CommandState::Stopped(cmd.clone())));
 
                         let handle = tokio::spawn(async move {
-                            &plugin.on_command_exited(cmd.clone());
+                            plugin.on_command_exited(cmd.clone());
                             let _ = tx.send(Some(cmd)).await;
                         });
 
@@ -95,11 +96,12 @@ where
 
                 let CommandSpawned {
                     data,
+                    pid,
                     join_handle,
                     killer,
                 } = cmd;
 
-                let data = plugin.initialize_command_data(data, stdout, stderr);
+                let data = plugin.initialize_command_data_with_pid(data, pid, stdout, stderr);
 
                 let mutex_ret = Arc::new(Mutex::new(CommandState::Spawned { data, killer }));
 
@@ -120,10 +122,10 @@ where
                                 *state = CommandState::Stopped(cmd.clone());
 
                                 if tx.is_closed() {
-                                    &plugin.on_command_exited(cmd);
+                                    plugin.on_command_exited(cmd);
                                     None
                                 } else {
-                                    &plugin.on_command_exited(cmd.clone());
+                                    plugin.on_command_exited(cmd.clone());
                                     Some(cmd)
                                 }
                             }
@@ -145,6 +147,8 @@ where
             .into_iter()
             .unzip();
 
+        plugin.on_commands_spawned();
+
         let command_count = commands.len();
         let commands_ret = Arc::new(commands);
 
@@ -162,23 +166,9 @@ where
                     let should_kill_all: bool = match &kill_behavior {
                         KillBehavior::None => false,
                         KillBehavior::WhenAnyExited => true,
-                        KillBehavior::WhenAnyExitedWithStatus(status) => match status {
-                            crate::ExitStatusPattern::Success => exited_cmd
-                                .exit_status
-                                .as_ref()
-                                .ok()
-                                .map_or(false, |s| s.success()),
-                            crate::ExitStatusPattern::Failed => exited_cmd
-                                .exit_status
-                                .as_ref()
-                                .ok()
-                                .map_or(true, |s| !s.success()),
-                            crate::ExitStatusPattern::StatusCode(code) => exited_cmd
-                                .exit_status
-                                .as_ref()
-                                .ok()
-                                .map_or(false, |s| s.code() == Some(*code)),
-                        },
+                        KillBehavior::WhenAnyExitedWithStatus(status) => {
+                            status.matches(&exited_cmd.exit_status, exited_cmd.spawned_at.is_some())
+                        }
                     };
 
                     if should_kill_all {
@@ -199,11 +189,8 @@ where
                     for state in commands.iter() {
                         let mut state = state.lock().unwrap();
 
-                        match &mut *state {
-                            CommandState::Spawned { killer, .. } => {
-                                killer.kill(reason.clone());
-                            }
-                            _ => {}
+                        if let CommandState::Spawned { killer, .. } = &mut *state {
+                            killer.kill(reason.clone());
                         }
                     }
 
@@ -314,9 +301,43 @@ impl<T, P: CommandSystemPlugin<T>> CommandSystem<T, P> {
     }
 }
 
+impl<P: CommandSystemPlugin<LabeledCommandData>> CommandSystem<LabeledCommandData, P> {
+    /// Wait for all commands to exit and report each of them
+    pub async fn wait_into_report(&mut self) -> CommandSystemReport {
+        let commands = self
+            .wait_iter_stopped_commands(|cmd| CommandReport::from_stopped(cmd))
+            .await
+            .collect();
+
+        CommandSystemReport { commands }
+    }
+}
+
+#[non_exhaustive]
 #[derive(Debug, Clone)]
 pub struct LabeledCommandData {
     pub label: Label,
+    /// Index of the command in [`RunConfig::commands`]
+    pub index: usize,
+    /// Color of the label, which may be ignored if output is not colorized
+    pub color: Color,
+    /// File to write output of the command
+    pub log_file: Option<PathBuf>,
+    /// Encoding of output of the command
+    pub encoding: Encoding,
+    pub stdout: OutputVisibility,
+    pub stderr: OutputVisibility,
+}
+
+impl std::fmt::Display for kill::KillCommandReason<LabeledCommandData> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            kill::KillCommandReason::OtherCommandExited(cmd) => {
+                write!(f, "command[{}] exited", cmd.data.label.label())
+            }
+            kill::KillCommandReason::MainProcessGotSignal => write!(f, "Ctrl-C signal"),
+        }
+    }
 }
 
 pub fn spawn_from_run_config_with_plugin<T, P>(
@@ -330,17 +351,78 @@ where
     let RunConfig {
         commands,
         max_label_length,
+        label_truncate,
+        label_align,
+        show_index,
         envs,
+        env_clear,
+        env_remove,
+        env_inherit,
         kill,
+        color,
+        child_env_defaults,
+        log_dir,
+        ..
     } = run_config;
 
-    let commands = commands.into_iter().map(|cmd| {
-        let (cmd, label) = cmd.into_tokio_command_and_label(envs.as_ref());
+    let child_env_defaults = child_env_defaults.unwrap_or_else(|| color.child_env_defaults());
+    // child env defaults have lower precedence than envs
+    let inherited_envs: Vec<(&String, &String)> = child_env_defaults
+        .iter()
+        .chain(envs.iter().flatten())
+        .collect();
+
+    // padding shrinks to the longest label
+    let real_max_label_length = commands
+        .iter()
+        .map(|cmd| cmd.label_length())
+        .max()
+        .unwrap_or(0);
+    let max_label_length = match max_label_length {
+        0 => real_max_label_length,
+        v => cmp::min(v, real_max_label_length),
+    };
+    let index_width = commands.len().saturating_sub(1).to_string().len();
+
+    let commands = commands.into_iter().enumerate().map(|(index, mut cmd)| {
+        let label = cmd.label_or_default().into_owned();
+        let color = cmd.color.unwrap_or_else(|| Color::from_palette(index));
+        let stdout = cmd.stdout.unwrap_or_default();
+        let stderr = cmd.stderr.unwrap_or_default();
+        let log_file = cmd.log_file_path(log_dir.as_deref());
+        let encoding = cmd.encoding.unwrap_or_default();
+
+        let label = Label::format(label, max_label_length, label_truncate, label_align);
+        let label = if show_index {
+            label.with_index(index, index_width)
+        } else {
+            label
+        };
+
+        cmd.inherit_env_isolation(env_clear, env_remove.as_deref(), env_inherit.as_deref());
+
+        // envs from env files can be interpolated
+        let cmd = cmd
+            .load_env_files()
+            .and_then(|()| {
+                cmd.interpolate(envs.as_ref())
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+            })
+            .map(|()| {
+                cmd.into_tokio_command_and_label(Some(inherited_envs.iter().copied()))
+                    .0
+            });
 
         (
             cmd,
             LabeledCommandData {
-                label: Label::from_label(label, max_label_length),
+                label,
+                index,
+                color,
+                log_file,
+                encoding,
+                stdout,
+                stderr,
             },
         )
     });
@@ -360,6 +442,23 @@ pub trait CommandSystemPlugin<T>: Send + Sync + 'static + Sized {
         stderr: ChildStderr,
     ) -> T;
 
+    /// Like [`initialize_command_data`](Self::initialize_command_data),
+    /// with the pid of the spawned command.
+    ///
+    /// Defaults to `initialize_command_data`, ignoring `pid`.
+    fn initialize_command_data_with_pid(
+        &self,
+        data: Self::CommandInitialData,
+        _pid: Option<u32>,
+        stdout: ChildStdout,
+        stderr: ChildStderr,
+    ) -> T {
+        self.initialize_command_data(data, stdout, stderr)
+    }
+
+    /// Called once after all commands are spawned or failed to spawn
+    fn on_commands_spawned(&self) {}
+
     fn on_command_exited(&self, _cmd: Arc<CommandStopped<T, T>>) {}
 
     fn join(&self) -> Option<JoinHandle<()>> {
@@ -0,0 +1,359 @@
+//! A virtual terminal line model for output of commands.
+//!
+//! Output of commands is piped to runcc, but many programs still write progress bars
+//! with `\r`, cursor movement and erase sequences.
+//! [`VirtualLine`] applies them to the line being written,
+//! so that only the final rendered lines are printed.
+//!
+//! Lines are emitted as soon as they end with `\n`,
+//! so cursor up / down can't rewrite lines which are already emitted and are ignored.
+//!
+//! See: https://en.wikipedia.org/wiki/ANSI_escape_code#CSI_(Control_Sequence_Introducer)_sequences
+
+use std::mem;
+
+/// The column a cursor move can reach beyond the end of the line
+const MAX_CURSOR_COLUMN: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cell {
+    /// Escape sequences like colors to write before `ch`
+    style: String,
+    ch: char,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseState {
+    Ground,
+    /// After `ESC`
+    Escape,
+    /// Control Sequence Introducer `ESC [` with parameters and intermediates
+    Csi(String),
+    /// Operating System Command `ESC ]` like hyperlinks, and whether the last char is `ESC`
+    Osc(String, bool),
+}
+
+/// The line being written by a command, with a cursor.
+///
+/// Escape sequences which can be split across reads are handled by feeding chunks.
+#[derive(Debug, Clone)]
+pub(crate) struct VirtualLine {
+    cells: Vec<Cell>,
+    cursor: usize,
+    /// Escape sequences which will be attached to the next written char
+    pending_style: String,
+    state: ParseState,
+    /// Whether to interpret `\r` and escape sequences, or keep them as is
+    interpret: bool,
+    /// The line rendered before the last `\r`, like a frame of a spinner
+    overwritten: Option<String>,
+}
+
+impl Default for VirtualLine {
+    fn default() -> Self {
+        Self::new(cfg!(feature = "auto_ansi_escape"))
+    }
+}
+
+impl VirtualLine {
+    pub(crate) fn new(interpret: bool) -> Self {
+        Self {
+            cells: vec![],
+            cursor: 0,
+            pending_style: String::new(),
+            state: ParseState::Ground,
+            interpret,
+            overwritten: None,
+        }
+    }
+
+    /// Whether nothing has been written to the line
+    pub(crate) fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.pending_style.is_empty()
+    }
+
+    /// Feed output of a command, pushing completed lines to `lines`
+    pub(crate) fn feed(&mut self, s: &str, lines: &mut Vec<String>) {
+        for c in s.chars() {
+            if !self.interpret {
+                if c == '\n' {
+                    let mut line = self.take_line();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                    lines.push(line);
+                } else {
+                    self.write_char(c);
+                }
+                continue;
+            }
+
+            match mem::replace(&mut self.state, ParseState::Ground) {
+                ParseState::Ground => match c {
+                    '\n' => lines.push(self.take_line()),
+                    '\r' => {
+                        if !self.cells.is_empty() {
+                            self.overwritten = Some(self.render());
+                        }
+                        self.cursor = 0;
+                    }
+                    '\x08' => self.cursor = self.cursor.saturating_sub(1),
+                    '\x1B' => self.state = ParseState::Escape,
+                    '\t' => self.write_char(c),
+                    c if c.is_control() => {}
+                    c => self.write_char(c),
+                },
+                ParseState::Escape => match c {
+                    '[' => self.state = ParseState::Csi(String::new()),
+                    ']' => self.state = ParseState::Osc(String::new(), false),
+                    // intermediates of two-character sequences like `ESC ( B`
+                    ' '..='/' => self.state = ParseState::Escape,
+                    _ => {}
+                },
+                ParseState::Csi(mut params) => {
+                    if ('@'..='~').contains(&c) {
+                        self.apply_csi(&params, c);
+                    } else {
+                        params.push(c);
+                        self.state = ParseState::Csi(params);
+                    }
+                }
+                ParseState::Osc(mut content, after_escape) => {
+                    if c == '\x07' || (after_escape && c == '\\') {
+                        content.push(c);
+                        self.pending_style.push_str("\x1B]");
+                        self.pending_style.push_str(&content);
+                    } else {
+                        content.push(c);
+                        self.state = ParseState::Osc(content, c == '\x1B');
+                    }
+                }
+            }
+        }
+    }
+
+    /// The line as it is rendered now
+    pub(crate) fn render(&self) -> String {
+        let mut line = String::new();
+        for cell in &self.cells {
+            line.push_str(&cell.style);
+            line.push(cell.ch);
+        }
+        line.push_str(&self.pending_style);
+        line
+    }
+
+    /// Take the line which is not ended yet, if any.
+    ///
+    /// Following output is written to a new line, from the first column.
+    pub(crate) fn take_partial(&mut self) -> Option<String> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.take_line())
+        }
+    }
+
+    /// Take the line as it was rendered before the last `\r`, if any.
+    ///
+    /// Unlike [`take_partial`](Self::take_partial), the line being written is kept,
+    /// so that a line redrawn with `\r` like a spinner can be flushed while it's still redrawn.
+    pub(crate) fn take_overwritten(&mut self) -> Option<String> {
+        self.overwritten.take()
+    }
+
+    /// Take the rest of output when the command exits, if any
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        self.state = ParseState::Ground;
+        self.take_partial()
+    }
+
+    fn take_line(&mut self) -> String {
+        let line = self.render();
+        self.cells.clear();
+        self.cursor = 0;
+        self.pending_style.clear();
+        self.overwritten = None;
+        line
+    }
+
+    fn write_char(&mut self, ch: char) {
+        if self.cursor > self.cells.len() {
+            self.cells.resize(
+                self.cursor,
+                Cell {
+                    style: String::new(),
+                    ch: ' ',
+                },
+            );
+        }
+
+        let cell = Cell {
+            style: mem::take(&mut self.pending_style),
+            ch,
+        };
+
+        if self.cursor == self.cells.len() {
+            self.cells.push(cell);
+        } else {
+            self.cells[self.cursor] = cell;
+        }
+
+        self.cursor += 1;
+    }
+
+    /// Erase from the cursor to the end (0), from the start to the cursor (1) or the whole line (2)
+    fn erase(&mut self, mode: usize) {
+        match mode {
+            0 => self.cells.truncate(self.cursor),
+            1 => {
+                let end = (self.cursor + 1).min(self.cells.len());
+                for cell in &mut self.cells[..end] {
+                    cell.ch = ' ';
+                }
+            }
+            _ => self.cells.clear(),
+        }
+    }
+
+    /// Move the cursor to `column`, but not beyond [`MAX_CURSOR_COLUMN`] or the end of the line,
+    /// so that a huge cursor move doesn't fill the line with a huge number of spaces
+    fn move_cursor(&mut self, column: usize) {
+        self.cursor = column.min(MAX_CURSOR_COLUMN.max(self.cells.len()));
+    }
+
+    fn apply_csi(&mut self, params: &str, final_char: char) {
+        let mut numbers = params.split(';').map(|n| n.parse::<usize>().ok());
+        let first = numbers.next().flatten();
+        // most sequences treat 0 as 1
+        let count = first.unwrap_or(1).max(1);
+
+        match final_char {
+            // Select Graphic Rendition, like colors
+            'm' => {
+                self.pending_style.push_str("\x1B[");
+                self.pending_style.push_str(params);
+                self.pending_style.push('m');
+            }
+            // Erase in Line
+            'K' => self.erase(first.unwrap_or(0)),
+            // Erase in Display, which only affects the current line
+            'J' => self.erase(first.unwrap_or(0)),
+            // Cursor Forward / Back
+            'C' => self.move_cursor(self.cursor.saturating_add(count)),
+            'D' => self.cursor = self.cursor.saturating_sub(count),
+            // Cursor Horizontal Absolute
+            'G' | '`' => self.move_cursor(count - 1),
+            // Cursor Position, where the row is ignored
+            'H' | 'f' => self.move_cursor(numbers.next().flatten().unwrap_or(1).max(1) - 1),
+            // Cursor Next / Previous Line
+            'E' | 'F' => self.cursor = 0,
+            // cursor up / down, hide / show cursor and others
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VirtualLine;
+
+    fn render_lines(chunks: &[&str]) -> (Vec<String>, String) {
+        let mut line = VirtualLine::new(true);
+        let mut lines = vec![];
+        for chunk in chunks {
+            line.feed(chunk, &mut lines);
+        }
+        (lines, line.render())
+    }
+
+    #[test]
+    fn carriage_return_and_erase() {
+        assert_eq!(
+            render_lines(&["Downloading 10%\rDownloading 100%\n"]),
+            (vec!["Downloading 100%".to_string()], String::new())
+        );
+        assert_eq!(
+            render_lines(&["Downloading 10%\r\x1B[2KDone\r\n"]),
+            (vec!["Done".to_string()], String::new())
+        );
+        assert_eq!(render_lines(&["abcdef\rxy\x1B[K"]).1, "xy");
+        assert_eq!(render_lines(&["abcdef\x1B[3D\x1B[1K"]).1, "    ef");
+        assert_eq!(render_lines(&["abc\x1B[1Gx\x1B[5Gy"]).1, "xbc y");
+        assert_eq!(render_lines(&["abc\x08\x08x"]).1, "axc");
+    }
+
+    #[test]
+    fn clamp_cursor_moves() {
+        for seq in ["\x1B[999999999G", "\x1B[1;999999999H", "\x1B[999999999C"] {
+            let (_, line) = render_lines(&["a", seq, "\x1B[999999999C", "b"]);
+            assert_eq!(line.len(), super::MAX_CURSOR_COLUMN + 1, "{:?}", seq);
+            assert!(line.ends_with(" b"));
+        }
+
+        let (_, line) = render_lines(&["a\x1B[18446744073709551615Cb"]);
+        assert!(line.len() <= super::MAX_CURSOR_COLUMN + 1);
+    }
+
+    #[test]
+    fn keep_styles() {
+        assert_eq!(
+            render_lines(&["\x1B[32m 50%\x1B[0m\r\x1B[32m100%\x1B[0m\n"]).0,
+            vec!["\x1B[0m\x1B[32m100%\x1B[0m".to_string()]
+        );
+        assert_eq!(
+            render_lines(&["\x1B]8;;https://a.b\x1B\\link\x1B]8;;\x07"]).1,
+            "\x1B]8;;https://a.b\x1B\\link\x1B]8;;\x07"
+        );
+    }
+
+    #[test]
+    fn escapes_split_across_chunks() {
+        assert_eq!(
+            render_lines(&["10%\x1B", "[2", "K\r", "\x1B[?25l20%\n", "\x1B(Bnext"]),
+            (vec!["20%".to_string()], "next".to_string())
+        );
+    }
+
+    #[test]
+    fn take_partial_line() {
+        let mut line = VirtualLine::new(true);
+        let mut lines = vec![];
+        line.feed("Password: \x1B[", &mut lines);
+        assert_eq!(line.take_partial().as_deref(), Some("Password: "));
+        assert_eq!(line.take_partial(), None);
+        line.feed("1mok\n", &mut lines);
+        assert_eq!(lines, vec!["\x1B[1mok".to_string()]);
+    }
+
+    #[test]
+    fn take_overwritten_line() {
+        let mut line = VirtualLine::new(true);
+        let mut lines = vec![];
+        line.feed("\r| 1%\r/ 2%\r- 3", &mut lines);
+        assert_eq!(line.take_overwritten().as_deref(), Some("/ 2%"));
+        assert_eq!(line.take_overwritten(), None);
+        assert_eq!(line.render(), "- 3%");
+        line.feed("%\r\n", &mut lines);
+        assert_eq!(line.take_overwritten(), None);
+        assert_eq!(lines, vec!["- 3%".to_string()]);
+    }
+
+    #[test]
+    fn ignore_vertical_movement() {
+        assert_eq!(
+            render_lines(&["a\n\x1B[1A\x1B[2Kb\n"]).0,
+            vec!["a".to_string(), "b".to_string()]
+        );
+    }
+
+    #[test]
+    fn keep_escapes_without_interpreting() {
+        let mut line = VirtualLine::new(false);
+        let mut lines = vec![];
+        line.feed("a\rb\x1B[2K\r\nc", &mut lines);
+        assert_eq!(lines, vec!["a\rb\x1B[2K".to_string()]);
+        assert_eq!(line.finish().as_deref(), Some("c"));
+        assert_eq!(line.finish(), None);
+    }
+}
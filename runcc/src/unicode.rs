@@ -0,0 +1,84 @@
+//! Display width and grapheme clusters of strings in terminals,
+//! with [`unicode_width`] and [`unicode_segmentation`].
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns taken by `s` in a terminal
+pub(crate) fn str_width(s: &str) -> usize {
+    graphemes(s).map(UnicodeWidthStr::width).sum()
+}
+
+/// Split `s` into extended grapheme clusters
+fn graphemes(s: &str) -> impl DoubleEndedIterator<Item = &str> {
+    s.graphemes(true)
+}
+
+/// The longest prefix of `s` which fits in `width` columns, on grapheme boundaries
+pub(crate) fn take_width(s: &str, width: usize) -> &str {
+    let mut taken = 0;
+    let mut end = 0;
+
+    for grapheme in graphemes(s) {
+        taken += grapheme.width();
+        if taken > width {
+            break;
+        }
+        end += grapheme.len();
+    }
+
+    &s[..end]
+}
+
+/// The longest suffix of `s` which fits in `width` columns, on grapheme boundaries
+pub(crate) fn take_width_end(s: &str, width: usize) -> &str {
+    let mut taken = 0;
+    let mut start = s.len();
+
+    for grapheme in graphemes(s).rev() {
+        taken += grapheme.width();
+        if taken > width {
+            break;
+        }
+        start -= grapheme.len();
+    }
+
+    &s[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{graphemes, str_width, take_width, take_width_end};
+
+    #[test]
+    fn width() {
+        for (s, width) in [
+            ("api", 3),
+            ("服务", 4),
+            ("e\u{301}", 1),
+            ("👍🏽", 2),
+            ("👨‍👩‍👧", 2),
+            ("🇯🇵", 2),
+            ("❤️", 2),
+            ("ｗｅｂ", 6),
+        ] {
+            assert_eq!(str_width(s), width, "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn split_graphemes() {
+        assert_eq!(
+            graphemes("ae\u{301}👨‍👩‍👧🇯🇵🇺🇸x").collect::<Vec<_>>(),
+            vec!["a", "e\u{301}", "👨‍👩‍👧", "🇯🇵", "🇺🇸", "x"]
+        );
+    }
+
+    #[test]
+    fn take_graphemes_by_width() {
+        assert_eq!(take_width("服务api", 3), "服");
+        assert_eq!(take_width("服务api", 5), "服务a");
+        assert_eq!(take_width_end("api服务", 3), "务");
+        assert_eq!(take_width_end("ae\u{301}", 1), "e\u{301}");
+    }
+}